@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::slice;
+use biblatex::Entry;
+
+/// Contents of a `.bib` file
+///
+/// Entries are kept in the order in which they appear, and can be looked up
+/// by their cite key or by any of the alternative keys given in their `ids`
+/// field.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Bibliography {
+    entries: Vec<(String, Entry)>,
+    // positions of the entries, by cite key
+    keys: HashMap<String, usize>,
+    // positions of the entries, by the keys in their `ids` field
+    aliases: HashMap<String, usize>,
+    /// Contents of each `@preamble`, with any macros expanded
+    pub preambles: Vec<String>,
+}
+
+impl Bibliography {
+    pub fn new() -> Bibliography {
+        Bibliography::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // position of the entry with the given key or alternative key
+    fn position(&self, key: &str) -> Option<usize> {
+        self.keys.get(key).or_else(|| self.aliases.get(key)).cloned()
+    }
+
+    /// Whether some entry has the given cite key or alternative key
+    pub fn contains(&self, key: &str) -> bool {
+        self.position(key).is_some()
+    }
+
+    /// Whether some entry has the given cite key, leaving out alternative keys
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.keys.contains_key(key)
+    }
+
+    /// Cite key of the entry which the given cite key or alternative key
+    /// refers to
+    pub fn resolve(&self, key: &str) -> Option<&str> {
+        self.position(key).map(|i| self.entries[i].0.as_str())
+    }
+
+    /// Entry with the given cite key, or with it among its `ids`
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.position(key).map(|i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Entry> {
+        let i = self.position(key)?;
+        Some(&mut self.entries[i].1)
+    }
+
+    /// Adds an entry at the end, or replaces the entry with the same cite key
+    /// in its place, returning it
+    pub fn insert(&mut self, key: &str, entry: Entry) -> Option<Entry> {
+        if let Some(&i) = self.keys.get(key) {
+            let old = ::std::mem::replace(&mut self.entries[i].1, entry);
+            self.reindex();
+            return Some(old);
+        }
+        let i = self.entries.len();
+        for id in entry.ids() {
+            self.aliases.entry(id).or_insert(i);
+        }
+        self.keys.insert(key.to_string(), i);
+        self.entries.push((key.to_string(), entry));
+        None
+    }
+
+    /// Removes the entry with the given cite key, or with it among its `ids`
+    pub fn remove(&mut self, key: &str) -> Option<Entry> {
+        let i = self.position(key)?;
+        let (_, entry) = self.entries.remove(i);
+        self.reindex();
+        Some(entry)
+    }
+
+    // rebuilds the lookup tables after entries were moved or changed
+    fn reindex(&mut self) {
+        self.keys.clear();
+        self.aliases.clear();
+        for (i, (key, entry)) in self.entries.iter().enumerate() {
+            self.keys.entry(key.clone()).or_insert(i);
+            for id in entry.ids() {
+                self.aliases.entry(id).or_insert(i);
+            }
+        }
+    }
+
+    /// Cite key and entry for each of the entries, in order
+    pub fn entries(&self) -> &[(String, Entry)] {
+        &self.entries
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, (String, Entry)> {
+        self.entries.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|e| e.0.as_str())
+    }
+
+    /// Entries of the given type, e.g. `article`, along with their keys
+    pub fn of_type<'a>(&'a self, entry_type: &'a str) -> impl Iterator<Item = &'a (String, Entry)> {
+        self.entries.iter().filter(move |e| e.1.entry_type() == entry_type)
+    }
+}
+
+impl<'a> IntoIterator for &'a Bibliography {
+    type Item = &'a (String, Entry);
+    type IntoIter = slice::Iter<'a, (String, Entry)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+impl IntoIterator for Bibliography {
+    type Item = (String, Entry);
+    type IntoIter = ::std::vec::IntoIter<(String, Entry)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use biblatex::{Article, Book, Text};
+
+    fn article(title: &str) -> Entry {
+        Entry::Article(Article { title: Text::from(title), .. Article::default() })
+    }
+
+    #[test]
+    fn lookup() {
+        let mut bib = Bibliography::new();
+        let mut book = Entry::Book(Book::default());
+        book.extra_mut().insert("ids", "mac, maclane:1998");
+        assert_eq!(bib.insert("a", article("A")), None);
+        assert_eq!(bib.insert("maclane", book), None);
+        assert_eq!(bib.insert("b", article("B")), None);
+        assert_eq!(bib.len(), 3);
+        assert_eq!(bib.get("maclane:1998").map(Entry::entry_type), Some("book"));
+        assert!(bib.contains("mac"));
+        assert!(!bib.contains("c"));
+        assert!(!bib.contains_key("mac"));
+        assert_eq!(bib.resolve("mac"), Some("maclane"));
+
+        assert_eq!(bib.insert("a", article("A2")), Some(article("A")));
+        assert_eq!(bib.keys().collect::<Vec<_>>(), vec!["a", "maclane", "b"]);
+        assert_eq!(bib.of_type("article").map(|e| e.0.as_str()).collect::<Vec<_>>(), vec!["a", "b"]);
+
+        assert!(bib.remove("mac").is_some());
+        assert_eq!(bib.get("b"), Some(&article("B")));
+        assert!(!bib.contains("maclane:1998"));
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt;
+
+/// Season, which EDTF writes in place of a month (21 to 24)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+/// Digits of a date which were left unspecified with `X`, in which case the
+/// date is read as the range of the dates it could be, as Biber does
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unspecified {
+    /// e.g. `199X`
+    YearInDecade,
+    /// e.g. `19XX`
+    YearInCentury,
+    /// e.g. `1999-XX`
+    MonthInYear,
+    /// e.g. `1999-01-XX`
+    DayInMonth,
+    /// e.g. `1999-XX-XX`
+    DayInYear,
+}
+
+/// Single date, given to the precision of a year, season, month or day
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct DatePart {
+    /// Year, numbered astronomically: 0 is 1 BCE, -1 is 2 BCE, etc.
+    pub year: i32,
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+    pub season: Option<Season>,
+    /// Marked as uncertain, e.g. `2004?`
+    pub uncertain: bool,
+    /// Marked as approximate, e.g. `2004~`
+    pub approximate: bool,
+}
+
+impl DatePart {
+    /// Date given by just its year
+    pub fn year(year: i32) -> DatePart {
+        DatePart { year, .. DatePart::default() }
+    }
+}
+
+/// Date or range of dates, as given in the `date`, `urldate`, `eventdate`
+/// and `origdate` fields, in ISO 8601 format with the EDTF extensions
+/// supported by Biber
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Date {
+    pub start: DatePart,
+    /// End of the range, if the date is one
+    pub end: Option<RangeEnd>,
+    /// Which digits were unspecified, if any, for a range derived from them
+    pub unspecified: Option<Unspecified>,
+}
+
+/// End of a range of dates
+#[derive(Clone, Debug, PartialEq)]
+pub enum RangeEnd {
+    /// Open-ended range, e.g. `2004/`
+    Open,
+    Date(DatePart),
+}
+
+impl Date {
+    /// Date given by just its year
+    pub fn year(year: i32) -> Date {
+        Date { start: DatePart::year(year), .. Date::default() }
+    }
+}
+
+impl fmt::Display for Season {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let code = match *self {
+            Season::Spring => 21,
+            Season::Summer => 22,
+            Season::Autumn => 23,
+            Season::Winter => 24,
+        };
+        write!(f, "{}", code)
+    }
+}
+
+// `-0044` for 45 BCE, and `Y10000` for years of more than four digits
+fn write_year(f: &mut fmt::Formatter, year: i32) -> fmt::Result {
+    if year.unsigned_abs() > 9999 {
+        write!(f, "Y")?;
+    }
+    match year < 0 {
+        true => write!(f, "-{:04}", year.unsigned_abs()),
+        false => write!(f, "{:04}", year),
+    }
+}
+
+impl fmt::Display for DatePart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_year(f, self.year)?;
+        if let Some(season) = self.season {
+            write!(f, "-{}", season)?;
+        }
+        if let Some(month) = self.month {
+            write!(f, "-{:02}", month)?;
+        }
+        if let Some(day) = self.day {
+            write!(f, "-{:02}", day)?;
+        }
+        match (self.uncertain, self.approximate) {
+            (true, true) => write!(f, "%"),
+            (true, false) => write!(f, "?"),
+            (false, true) => write!(f, "~"),
+            (false, false) => Ok(()),
+        }
+    }
+}
+
+/// Formats the date in the form in which it is read, e.g. `2004-02/2006`
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let start = &self.start;
+        match self.unspecified {
+            Some(Unspecified::YearInDecade) => write!(f, "{:03}X", start.year / 10),
+            Some(Unspecified::YearInCentury) => write!(f, "{:02}XX", start.year / 100),
+            Some(Unspecified::MonthInYear) => write!(f, "{:04}-XX", start.year),
+            Some(Unspecified::DayInMonth) => write!(f, "{:04}-{:02}-XX", start.year, start.month.unwrap_or(1)),
+            Some(Unspecified::DayInYear) => write!(f, "{:04}-XX-XX", start.year),
+            None => {
+                write!(f, "{}", start)?;
+                match self.end {
+                    Some(RangeEnd::Open) => write!(f, "/"),
+                    Some(RangeEnd::Date(ref end)) => write!(f, "/{}", end),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
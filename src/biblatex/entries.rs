@@ -1,149 +1,1354 @@
-use biblatex::{Field, NameList, Range};
+use std::error;
+use std::fmt;
+use biblatex::{Field, NameList, Range, ExtraFields, OriginalNames, Text, Date};
+use parser::{self, canonical_name, Options};
+use writer;
 
-/// A bibliographic entry
-#[derive(PartialEq, Debug)]
-pub enum Entry {
-    /// Article in a journal or other periodical forming a self-contained unit
-    Article(Article),
-    /// Article in a conference proceedings
-    InProceedings(InProceedings),
-}
-
-impl Entry {
-    pub fn author(&self) -> &NameList {
-        match *self {
-            Entry::InProceedings(InProceedings { ref author, .. }) |
-            Entry::Article(Article { ref author, ..}) => author,
+// Every entry type has its own struct, in which required fields are stored
+// directly and optional fields are wrapped in an `Option`. Since there are
+// a few dozen of them, the structs, their `Entry` variants and the `new_*`
+// constructors are all generated from the declarations at the bottom of
+// this file.
+//
+// `required` lists the fields without which the constructor fails, and each
+// group in `one_of` lists optional fields of which at least one must be set
+// (e.g. `author` or `editor` for a `@misc`). Fields which a type does not
+// declare, such as a `publisher` for an `@article`, are kept among its extra
+// fields.
+macro_rules! entry_types {
+    ($(
+        $(#[$attr:meta])*
+        $variant:ident($name:expr), $constructor:ident {
+            required { $($req:ident: $req_ty:ty = $req_field:ident),* $(,)* }
+            optional { $($opt:ident: $opt_ty:ty = $opt_field:ident),* $(,)* }
+            one_of { $([$($alt:ident),+])* }
         }
-    }
-
-    pub fn title(&self) -> &str {
-        match *self {
-            Entry::InProceedings(InProceedings { ref title, .. }) |
-            Entry::Article(Article { ref title, ..}) => title,
+    )*) => {
+        /// A bibliographic entry
+        #[derive(Clone, PartialEq, Debug)]
+        pub enum Entry {
+            $($(#[$attr])* $variant($variant),)*
         }
-    }
 
-    pub fn year(&self) -> i32 {
-        match *self {
-            Entry::InProceedings(InProceedings { year, .. }) |
-            Entry::Article(Article { year, ..}) => year,
-        }
-    }
+        impl Entry {
+            /// Name of the BibLaTeX entry type, e.g. `article`
+            pub fn entry_type(&self) -> &'static str {
+                match *self {
+                    $(Entry::$variant(_) => $name,)*
+                }
+            }
 
-    /// Attempts to instantiate a new `InProceedings`
-    pub fn new_in_proceedings(fields: Vec<Field>) -> Option<Entry> {
-        let mut inproc = InProceedings::default();
+            /// Fields of the entry which are not modelled by its type
+            pub fn extra(&self) -> &ExtraFields {
+                match *self {
+                    $(Entry::$variant(ref e) => &e.extra,)*
+                }
+            }
 
-        // required fields
-        let mut has_year = false;
-        let mut has_title = false;
-        let mut has_author = false;
-        let mut has_book_title = false;
+            pub fn extra_mut(&mut self) -> &mut ExtraFields {
+                match *self {
+                    $(Entry::$variant(ref mut e) => &mut e.extra,)*
+                }
+            }
 
-        for field in fields {
-            match field {
-                Field::Year(y) => {
-                    inproc.year = y;
-                    has_year = true;
+            /// Names under which fields were given, where they differ from
+            /// the BibLaTeX ones
+            pub fn original_names(&self) -> &OriginalNames {
+                match *self {
+                    $(Entry::$variant(ref e) => &e.original_names,)*
                 }
-                Field::Title(s) => {
-                    inproc.title = s;
-                    has_title = true;
+            }
+
+            pub fn original_names_mut(&mut self) -> &mut OriginalNames {
+                match *self {
+                    $(Entry::$variant(ref mut e) => &mut e.original_names,)*
                 }
-                Field::BookTitle(s) => {
-                    inproc.book_title = s;
-                    has_book_title = true;
+            }
+
+            /// Fields of the entry, with the extra fields last
+            pub fn fields(&self) -> Vec<Field> {
+                match *self {
+                    $(Entry::$variant(ref e) => e.fields(),)*
                 }
-                Field::Author(a) => {
-                    inproc.author = a;
-                    has_author = true;
+            }
+
+            /// Field with the given name, whether it is modelled by the type
+            /// of the entry or an extra field
+            pub fn get(&self, name: &str) -> Option<Field> {
+                match *self {
+                    $(Entry::$variant(ref e) => e.get(name),)*
                 }
-                Field::Pages(ps) => inproc.pages = Some(ps),
-                Field::Editor(es) => inproc.editor = Some(es),
-                Field::Volume(v) => inproc.volume = Some(v),
-                Field::Series(s) => inproc.series = Some(s),
-                Field::Url(u) => inproc.url = Some(u),
-                _ => (),
             }
-        }
 
-        if has_year && has_title && has_author && has_book_title {
-            Some(Entry::InProceedings(inproc))
-        } else {
-            None
+            /// Sets a field, returning its previous value, or fails if its
+            /// value cannot be parsed into a field which the type models
+            pub fn set(&mut self, field: Field) -> Result<Option<Field>, InvalidEntry> {
+                match *self {
+                    $(Entry::$variant(ref mut e) => e.set(field),)*
+                }
+            }
+
+            /// Removes a field, returning its value, or fails if the entry
+            /// cannot do without it
+            pub fn remove(&mut self, name: &str) -> Result<Option<Field>, InvalidEntry> {
+                match *self {
+                    $(Entry::$variant(ref mut e) => e.remove(name),)*
+                }
+            }
+
+            fn members(&self) -> &dyn Members {
+                match *self {
+                    $(Entry::$variant(ref e) => e,)*
+                }
+            }
+
+            $(
+                #[doc = concat!("Attempts to instantiate a new `", stringify!($variant), "`, along with \
+                                 the names of the fields which the type does not model and which are \
+                                 kept as extra fields")]
+                pub fn $constructor(fields: Vec<Field>) -> Result<(Entry, Vec<String>), InvalidEntry> {
+                    let mut entry = $variant::default();
+
+                    // required fields
+                    $(let mut $req = false;)*
+                    let mut duplicated: Vec<String> = Vec::new();
+                    let mut unexpected: Vec<String> = Vec::new();
+
+                    for field in fields {
+                        let name = field.name().to_string();
+                        let seen = match field {
+                            $(Field::$req_field(v) => {
+                                let seen = $req;
+                                entry.$req = v;
+                                $req = true;
+                                seen
+                            })*
+                            $(Field::$opt_field(v) => entry.$opt.replace(v).is_some(),)*
+                            // fields without a model are kept as they are
+                            Field::Unknown(name, value) => {
+                                entry.extra.insert(&name, &value).is_some()
+                            }
+                            // as are modelled fields which the type does not
+                            // declare, as Biber accepts them, but they are
+                            // reported
+                            other => {
+                                if !unexpected.contains(&name) {
+                                    unexpected.push(name.clone());
+                                }
+                                entry.extra.insert(&name, &writer::value(&other)).is_some()
+                            }
+                        };
+                        if seen && !duplicated.contains(&name) {
+                            duplicated.push(name);
+                        }
+                    }
+
+                    // the name of a missing field is taken from a dummy
+                    // instance of it, since there is no value to go by
+                    let requirements: Vec<(bool, String)> = vec![
+                        $(($req, Field::$req_field(Default::default()).name().to_string()),)*
+                    ];
+                    let mut missing: Vec<String> = requirements.into_iter()
+                        .filter(|&(present, _)| !present)
+                        .map(|(_, name)| name)
+                        .collect();
+                    missing.extend(entry.missing_groups());
+
+                    if missing.is_empty() && duplicated.is_empty() {
+                        Ok((Entry::$variant(entry), unexpected))
+                    } else {
+                        Err(InvalidEntry { missing, duplicated, invalid: unexpected })
+                    }
+                }
+            )*
         }
-    }
 
-    /// Attempts to instantiate a new `Article`
-    pub fn new_article(fields: Vec<Field>) -> Option<Entry> {
-        let mut article = Article::default();
+        $(
+            #[derive(Clone, PartialEq, Debug, Default)]
+            pub struct $variant {
+                $(pub $req: $req_ty,)*
+                $(pub $opt: Option<$opt_ty>,)*
+                pub extra: ExtraFields,
+                pub original_names: OriginalNames,
+            }
+
+            impl $variant {
+                /// Fields of the entry, in the order in which they are
+                /// declared, with the extra fields last
+                pub fn fields(&self) -> Vec<Field> {
+                    let mut fields = vec![$(Field::$req_field(self.$req.clone())),*];
+                    $(if let Some(ref v) = self.$opt {
+                        fields.push(Field::$opt_field(v.clone()));
+                    })*
+                    for (name, value) in self.extra.iter() {
+                        fields.push(Field::Unknown(name.to_string(), value.to_string()));
+                    }
+                    fields
+                }
 
-        // required fields
-        let mut has_year = false;
-        let mut has_title = false;
-        let mut has_author = false;
-        let mut has_journal_title = false;
+                /// Field with the given name, which may be a legacy alias such
+                /// as `journal`, whether it is modelled by the type or an
+                /// extra field
+                pub fn get(&self, name: &str) -> Option<Field> {
+                    let given = name.to_lowercase();
+                    let name = canonical_name(name);
+                    $(if name == Field::$req_field(Default::default()).name() {
+                        return Some(Field::$req_field(self.$req.clone()));
+                    })*
+                    $(if name == Field::$opt_field(Default::default()).name() {
+                        return self.$opt.clone().map(Field::$opt_field);
+                    })*
+                    // extra fields are kept under an alias when the type does
+                    // not model the field, e.g. `address` for an `@article`
+                    let name = match self.extra.contains(&name) {
+                        true => name,
+                        false => given,
+                    };
+                    self.extra.get(&name).map(|value| Field::Unknown(name.clone(), value.to_string()))
+                }
 
-        for field in fields {
-            match field {
-                Field::Year(y) => {
-                    article.year = y;
-                    has_year = true;
+                /// Sets a field, returning its previous value
+                ///
+                /// Fields which the type does not model are kept as extra
+                /// fields, as the constructor does, while an unknown field
+                /// whose name (or alias) is that of a field the type models
+                /// has its value parsed into that field, and fails if it
+                /// cannot be.
+                pub fn set(&mut self, field: Field) -> Result<Option<Field>, InvalidEntry> {
+                    match field {
+                        $(Field::$req_field(v) => {
+                            Ok(Some(Field::$req_field(::std::mem::replace(&mut self.$req, v))))
+                        })*
+                        $(Field::$opt_field(v) => Ok(self.$opt.replace(v).map(Field::$opt_field)),)*
+                        Field::Unknown(name, value) => {
+                            let canonical = canonical_name(&name);
+                            if $variant::models(&canonical) {
+                                return match parser::field(&canonical, &value, &Options::default()) {
+                                    Ok(field) => self.set(field),
+                                    Err(_) => Err(InvalidEntry::invalid(&canonical)),
+                                };
+                            }
+                            // an alias is kept as it was given, as the parser
+                            // does, unless the field is already there
+                            let name = match self.extra.contains(&canonical) {
+                                true => canonical,
+                                false => name.to_lowercase(),
+                            };
+                            Ok(self.extra.insert(&name, &value).map(|old| Field::Unknown(name, old)))
+                        }
+                        other => {
+                            let name = other.name().to_string();
+                            Ok(self.extra.insert(&name, &writer::value(&other)).map(|old| Field::Unknown(name, old)))
+                        }
+                    }
                 }
-                Field::Title(s) => {
-                    article.title = s;
-                    has_title = true;
+
+                // whether the type models the field with the given BibLaTeX
+                // name
+                fn models(name: &str) -> bool {
+                    $(name == Field::$req_field(Default::default()).name() ||)*
+                    $(name == Field::$opt_field(Default::default()).name() ||)*
+                    false
                 }
-                Field::JournalTitle(s) => {
-                    article.journal_title = s;
-                    has_journal_title = true
+
+                /// Removes a field, given by its name or an alias of it,
+                /// returning its value, or fails if it is required
+                pub fn remove(&mut self, name: &str) -> Result<Option<Field>, InvalidEntry> {
+                    let given = name.to_lowercase();
+                    let name = canonical_name(name);
+                    $(if name == Field::$req_field(Default::default()).name() {
+                        return Err(InvalidEntry::missing(&name));
+                    })*
+                    $(if name == Field::$opt_field(Default::default()).name() {
+                        let removed = self.$opt.take();
+                        // the field may be the only one of a group which is
+                        // required
+                        if let Some(group) = self.missing_groups().pop() {
+                            self.$opt = removed;
+                            return Err(InvalidEntry::missing(&group));
+                        }
+                        self.original_names.remove(&name);
+                        return Ok(removed.map(Field::$opt_field));
+                    })*
+                    let name = match self.extra.contains(&name) {
+                        true => name,
+                        false => given,
+                    };
+                    self.original_names.remove(&name);
+                    Ok(self.extra.remove(&name).map(|value| Field::Unknown(name, value)))
                 }
-                Field::Author(a) => {
-                    article.author = a;
-                    has_author = true;
+
+                // groups of fields of which at least one is required, but
+                // none is set, e.g. `author/editor`
+                fn missing_groups(&self) -> Vec<String> {
+                    let groups: Vec<(bool, String)> = vec![
+                        $(($(self.$alt.is_some())||+, [$(stringify!($alt)),+].join("/")),)*
+                    ];
+                    groups.into_iter().filter(|&(present, _)| !present).map(|(_, group)| group).collect()
                 }
-                Field::Pages(ps) => article.pages = Some(ps),
-                Field::Editor(es) => article.editor = Some(es),
-                Field::Volume(v) => article.volume = Some(v),
-                Field::Series(s) => article.series = Some(s),
-                Field::Issue(i) => article.issue = Some(i),
-                Field::Url(u) => article.url = Some(u),
-                _ => (),
             }
-        }
 
-        if has_year && has_title && has_author && has_journal_title {
-            Some(Entry::Article(article))
-        } else {
-            None
+            impl Members for $variant {
+                $(fn $req(&self) -> Option<&$req_ty> { Some(&self.$req) })*
+                $(fn $opt(&self) -> Option<&$opt_ty> { self.$opt.as_ref() })*
+            }
+        )*
+    }
+}
+
+/// Reasons why an entry could not be instantiated
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidEntry {
+    /// Required fields which were not given; fields of which at least one is
+    /// required are listed together, e.g. `author/editor`
+    pub missing: Vec<String>,
+    /// Fields which were given more than once
+    pub duplicated: Vec<String>,
+    /// Fields which the type does not model, which are kept as extra fields
+    /// if the entry is otherwise valid, or whose value could not be parsed
+    pub invalid: Vec<String>,
+}
+
+// formats a list of field names, e.g. "fields `a`, `b`"
+fn field_list(names: &[String]) -> String {
+    let quoted: Vec<String> = names.iter().map(|n| format!("`{}`", n)).collect();
+    match quoted.len() {
+        1 => format!("field {}", quoted[0]),
+        _ => format!("fields {}", quoted.join(", ")),
+    }
+}
+
+impl InvalidEntry {
+    // an entry lacking the given field
+    fn missing(name: &str) -> InvalidEntry {
+        InvalidEntry { missing: vec![name.to_string()], duplicated: vec![], invalid: vec![] }
+    }
+
+    // an entry with a field whose value is not valid
+    fn invalid(name: &str) -> InvalidEntry {
+        InvalidEntry { missing: vec![], duplicated: vec![], invalid: vec![name.to_string()] }
+    }
+}
+
+impl fmt::Display for InvalidEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut reasons = Vec::new();
+        if !self.missing.is_empty() {
+            reasons.push(format!("missing required {}", field_list(&self.missing)));
+        }
+        if !self.duplicated.is_empty() {
+            reasons.push(format!("duplicated {}", field_list(&self.duplicated)));
         }
+        if !self.invalid.is_empty() {
+            reasons.push(format!("{} not valid for this entry type", field_list(&self.invalid)));
+        }
+        write!(f, "{}", reasons.join("; "))
     }
 }
 
-#[derive(PartialEq, Debug, Default)]
-pub struct InProceedings {
-    pub author: NameList,
-    pub title: String,
-    pub year: i32,
-    pub book_title: String,
-    pub editor: Option<NameList>,
-    pub volume: Option<String>,
-    pub series: Option<String>,
-    pub pages: Option<Vec<Range>>,
-    pub url: Option<String>,
+impl error::Error for InvalidEntry {}
+
+// Uniform access to the members of the entry structs: each entry type
+// overrides the getters for the fields it has, the rest return `None`.
+trait Members {
+    fn author(&self) -> Option<&NameList> { None }
+    fn editor(&self) -> Option<&NameList> { None }
+    fn title(&self) -> Option<&Text> { None }
+    fn main_title(&self) -> Option<&Text> { None }
+    fn journal_title(&self) -> Option<&Text> { None }
+    fn book_title(&self) -> Option<&Text> { None }
+    fn date(&self) -> Option<&Date> { None }
+    fn url_date(&self) -> Option<&Date> { None }
+    fn event_date(&self) -> Option<&Date> { None }
+    fn orig_date(&self) -> Option<&Date> { None }
+    fn volume(&self) -> Option<&String> { None }
+    fn edition(&self) -> Option<&String> { None }
+    fn series(&self) -> Option<&String> { None }
+    fn number(&self) -> Option<&String> { None }
+    fn issue(&self) -> Option<&String> { None }
+    fn chapter(&self) -> Option<&String> { None }
+    fn pages(&self) -> Option<&Vec<Range>> { None }
+    fn kind(&self) -> Option<&String> { None }
+    fn organization(&self) -> Option<&String> { None }
+    fn institution(&self) -> Option<&String> { None }
+    fn publisher(&self) -> Option<&String> { None }
+    fn location(&self) -> Option<&String> { None }
+    fn doi(&self) -> Option<&String> { None }
+    fn eprint(&self) -> Option<&String> { None }
+    fn url(&self) -> Option<&String> { None }
+    fn entry_set(&self) -> Option<&String> { None }
+}
+
+impl Entry {
+    pub fn author(&self) -> Option<&NameList> {
+        self.members().author()
+    }
+
+    pub fn editor(&self) -> Option<&NameList> {
+        self.members().editor()
+    }
+
+    pub fn title(&self) -> Option<&Text> {
+        self.members().title()
+    }
+
+    pub fn main_title(&self) -> Option<&Text> {
+        self.members().main_title()
+    }
+
+    pub fn journal_title(&self) -> Option<&Text> {
+        self.members().journal_title()
+    }
+
+    pub fn book_title(&self) -> Option<&Text> {
+        self.members().book_title()
+    }
+
+    pub fn date(&self) -> Option<&Date> {
+        self.members().date()
+    }
+
+    /// Year of the entry's date, or of the start of its date range
+    pub fn year(&self) -> Option<i32> {
+        self.members().date().map(|d| d.start.year)
+    }
+
+    pub fn url_date(&self) -> Option<&Date> {
+        self.members().url_date()
+    }
+
+    pub fn event_date(&self) -> Option<&Date> {
+        self.members().event_date()
+    }
+
+    pub fn orig_date(&self) -> Option<&Date> {
+        self.members().orig_date()
+    }
+
+    pub fn pages(&self) -> Option<&[Range]> {
+        self.members().pages().map(Vec::as_slice)
+    }
+
+    pub fn volume(&self) -> Option<&str> {
+        self.members().volume().map(String::as_str)
+    }
+
+    pub fn edition(&self) -> Option<&str> {
+        self.members().edition().map(String::as_str)
+    }
+
+    pub fn series(&self) -> Option<&str> {
+        self.members().series().map(String::as_str)
+    }
+
+    pub fn number(&self) -> Option<&str> {
+        self.members().number().map(String::as_str)
+    }
+
+    pub fn issue(&self) -> Option<&str> {
+        self.members().issue().map(String::as_str)
+    }
+
+    pub fn chapter(&self) -> Option<&str> {
+        self.members().chapter().map(String::as_str)
+    }
+
+    /// The `type` field, e.g. `phdthesis` for a `@thesis`
+    pub fn kind(&self) -> Option<&str> {
+        self.members().kind().map(String::as_str)
+    }
+
+    pub fn organization(&self) -> Option<&str> {
+        self.members().organization().map(String::as_str)
+    }
+
+    pub fn institution(&self) -> Option<&str> {
+        self.members().institution().map(String::as_str)
+    }
+
+    pub fn publisher(&self) -> Option<&str> {
+        self.members().publisher().map(String::as_str)
+    }
+
+    pub fn location(&self) -> Option<&str> {
+        self.members().location().map(String::as_str)
+    }
+
+    pub fn doi(&self) -> Option<&str> {
+        self.members().doi().map(String::as_str)
+    }
+
+    pub fn eprint(&self) -> Option<&str> {
+        self.members().eprint().map(String::as_str)
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.members().url().map(String::as_str)
+    }
+
+    pub fn entry_set(&self) -> Option<&str> {
+        self.members().entry_set().map(String::as_str)
+    }
+
+    /// Alternative cite keys given in the `ids` field, e.g. `doe2010` and
+    /// `doe:things` for `ids = {doe2010, doe:things}`
+    pub fn ids(&self) -> Vec<String> {
+        match self.extra().get("ids") {
+            Some(ids) => ids.split(',').map(str::trim).filter(|k| !k.is_empty()).map(String::from).collect(),
+            None => vec![],
+        }
+    }
 }
 
-#[derive(PartialEq, Debug, Default)]
-pub struct Article {
-    pub author: NameList,
-    pub title: String,
-    pub year: i32,
-    pub journal_title: String,
-    pub editor: Option<NameList>,
-    pub volume: Option<String>,
-    pub series: Option<String>,
-    pub issue: Option<String>,
-    pub pages: Option<Vec<Range>>,
-    pub url: Option<String>,
-}
\ No newline at end of file
+entry_types! {
+    /// Article in a journal or other periodical forming a self-contained unit
+    Article("article"), new_article {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            date: Date = Date,
+            journal_title: Text = JournalTitle,
+        }
+        optional {
+            editor: NameList = Editor,
+            volume: String = Volume,
+            series: String = Series,
+            number: String = Number,
+            issue: String = Issue,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Single-volume book with one or more authors
+    Book("book"), new_book {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            editor: NameList = Editor,
+            main_title: Text = MainTitle,
+            volume: String = Volume,
+            edition: String = Edition,
+            series: String = Series,
+            number: String = Number,
+            publisher: String = Publisher,
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Multi-volume book
+    MvBook("mvbook"), new_mv_book {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            editor: NameList = Editor,
+            edition: String = Edition,
+            series: String = Series,
+            number: String = Number,
+            publisher: String = Publisher,
+            location: String = Location,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Part of a book which forms a self-contained unit with its own title
+    InBook("inbook"), new_in_book {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            date: Date = Date,
+            book_title: Text = BookTitle,
+        }
+        optional {
+            editor: NameList = Editor,
+            main_title: Text = MainTitle,
+            volume: String = Volume,
+            edition: String = Edition,
+            series: String = Series,
+            number: String = Number,
+            publisher: String = Publisher,
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Book in a book, i.e. a work originally published as a stand-alone book
+    /// and reprinted in the collected works of an author
+    BookInBook("bookinbook"), new_book_in_book {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            date: Date = Date,
+            book_title: Text = BookTitle,
+        }
+        optional {
+            editor: NameList = Editor,
+            main_title: Text = MainTitle,
+            volume: String = Volume,
+            edition: String = Edition,
+            series: String = Series,
+            number: String = Number,
+            publisher: String = Publisher,
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Supplemental material in a book, such as a preface or an afterword
+    SuppBook("suppbook"), new_supp_book {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            date: Date = Date,
+            book_title: Text = BookTitle,
+        }
+        optional {
+            editor: NameList = Editor,
+            main_title: Text = MainTitle,
+            volume: String = Volume,
+            edition: String = Edition,
+            series: String = Series,
+            number: String = Number,
+            publisher: String = Publisher,
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Book-like work without a formal publisher or sponsoring institution
+    Booklet("booklet"), new_booklet {
+        required {
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            author: NameList = Author,
+            editor: NameList = Editor,
+            kind: String = Type,
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of { [author, editor] }
+    }
+
+    /// Single-volume collection with multiple, self-contained contributions
+    /// by distinct authors
+    Collection("collection"), new_collection {
+        required {
+            editor: NameList = Editor,
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            main_title: Text = MainTitle,
+            volume: String = Volume,
+            edition: String = Edition,
+            series: String = Series,
+            number: String = Number,
+            publisher: String = Publisher,
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Multi-volume collection
+    MvCollection("mvcollection"), new_mv_collection {
+        required {
+            editor: NameList = Editor,
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            edition: String = Edition,
+            series: String = Series,
+            number: String = Number,
+            publisher: String = Publisher,
+            location: String = Location,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Contribution to a collection which forms a self-contained unit with
+    /// its own author and title
+    InCollection("incollection"), new_in_collection {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            date: Date = Date,
+            book_title: Text = BookTitle,
+        }
+        optional {
+            editor: NameList = Editor,
+            main_title: Text = MainTitle,
+            volume: String = Volume,
+            edition: String = Edition,
+            series: String = Series,
+            number: String = Number,
+            publisher: String = Publisher,
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Supplemental material in a collection
+    SuppCollection("suppcollection"), new_supp_collection {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            date: Date = Date,
+            book_title: Text = BookTitle,
+        }
+        optional {
+            editor: NameList = Editor,
+            main_title: Text = MainTitle,
+            volume: String = Volume,
+            edition: String = Edition,
+            series: String = Series,
+            number: String = Number,
+            publisher: String = Publisher,
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Data set, such as raw research data
+    Dataset("dataset"), new_dataset {
+        required {
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            author: NameList = Author,
+            editor: NameList = Editor,
+            edition: String = Edition,
+            kind: String = Type,
+            series: String = Series,
+            number: String = Number,
+            organization: String = Organization,
+            publisher: String = Publisher,
+            location: String = Location,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of { [author, editor] }
+    }
+
+    /// Technical or other documentation, not necessarily in printed form
+    Manual("manual"), new_manual {
+        required {
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            author: NameList = Author,
+            editor: NameList = Editor,
+            edition: String = Edition,
+            kind: String = Type,
+            series: String = Series,
+            number: String = Number,
+            organization: String = Organization,
+            publisher: String = Publisher,
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of { [author, editor] }
+    }
+
+    /// Entry which does not fit into any other category
+    Misc("misc"), new_misc {
+        required {
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            author: NameList = Author,
+            editor: NameList = Editor,
+            kind: String = Type,
+            organization: String = Organization,
+            location: String = Location,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of { [author, editor] }
+    }
+
+    /// Online resource
+    Online("online"), new_online {
+        required {
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            author: NameList = Author,
+            editor: NameList = Editor,
+            organization: String = Organization,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of { [author, editor] [doi, eprint, url] }
+    }
+
+    /// Patent or patent request
+    Patent("patent"), new_patent {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            number: String = Number,
+            date: Date = Date,
+        }
+        optional {
+            kind: String = Type,
+            location: String = Location,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Complete issue of a periodical, such as a special issue of a journal
+    Periodical("periodical"), new_periodical {
+        required {
+            editor: NameList = Editor,
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            series: String = Series,
+            volume: String = Volume,
+            number: String = Number,
+            issue: String = Issue,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Supplemental material in a periodical
+    SuppPeriodical("suppperiodical"), new_supp_periodical {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            date: Date = Date,
+            journal_title: Text = JournalTitle,
+        }
+        optional {
+            editor: NameList = Editor,
+            volume: String = Volume,
+            series: String = Series,
+            number: String = Number,
+            issue: String = Issue,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Single-volume conference proceedings
+    Proceedings("proceedings"), new_proceedings {
+        required {
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            editor: NameList = Editor,
+            main_title: Text = MainTitle,
+            volume: String = Volume,
+            series: String = Series,
+            number: String = Number,
+            organization: String = Organization,
+            publisher: String = Publisher,
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+            event_date: Date = EventDate,
+        }
+        one_of {}
+    }
+
+    /// Multi-volume conference proceedings
+    MvProceedings("mvproceedings"), new_mv_proceedings {
+        required {
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            editor: NameList = Editor,
+            series: String = Series,
+            number: String = Number,
+            organization: String = Organization,
+            publisher: String = Publisher,
+            location: String = Location,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+            event_date: Date = EventDate,
+        }
+        one_of {}
+    }
+
+    /// Article in a conference proceedings
+    InProceedings("inproceedings"), new_in_proceedings {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            date: Date = Date,
+            book_title: Text = BookTitle,
+        }
+        optional {
+            editor: NameList = Editor,
+            main_title: Text = MainTitle,
+            volume: String = Volume,
+            series: String = Series,
+            number: String = Number,
+            organization: String = Organization,
+            publisher: String = Publisher,
+            location: String = Location,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+            event_date: Date = EventDate,
+        }
+        one_of {}
+    }
+
+    /// Single-volume work of reference, such as an encyclopedia or a dictionary
+    Reference("reference"), new_reference {
+        required {
+            editor: NameList = Editor,
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            main_title: Text = MainTitle,
+            volume: String = Volume,
+            edition: String = Edition,
+            series: String = Series,
+            number: String = Number,
+            publisher: String = Publisher,
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Multi-volume work of reference
+    MvReference("mvreference"), new_mv_reference {
+        required {
+            editor: NameList = Editor,
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            edition: String = Edition,
+            series: String = Series,
+            number: String = Number,
+            publisher: String = Publisher,
+            location: String = Location,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Article in a work of reference
+    InReference("inreference"), new_in_reference {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            date: Date = Date,
+            book_title: Text = BookTitle,
+        }
+        optional {
+            editor: NameList = Editor,
+            main_title: Text = MainTitle,
+            volume: String = Volume,
+            edition: String = Edition,
+            series: String = Series,
+            number: String = Number,
+            publisher: String = Publisher,
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Technical report, research report or white paper published by a
+    /// university or some other institution
+    Report("report"), new_report {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            kind: String = Type,
+            institution: String = Institution,
+            date: Date = Date,
+        }
+        optional {
+            number: String = Number,
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Entry set, i.e. a group of entries cited as a single reference
+    Set("set"), new_set {
+        required {
+            entry_set: String = EntrySet,
+        }
+        optional {}
+        one_of {}
+    }
+
+    /// Computer software
+    Software("software"), new_software {
+        required {
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            author: NameList = Author,
+            editor: NameList = Editor,
+            kind: String = Type,
+            organization: String = Organization,
+            location: String = Location,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of { [author, editor] }
+    }
+
+    /// Thesis written for an educational institution to satisfy the
+    /// requirements for a degree
+    Thesis("thesis"), new_thesis {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            kind: String = Type,
+            institution: String = Institution,
+            date: Date = Date,
+        }
+        optional {
+            location: String = Location,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Work with an author and a title which has not been formally published
+    Unpublished("unpublished"), new_unpublished {
+        required {
+            author: NameList = Author,
+            title: Text = Title,
+            date: Date = Date,
+        }
+        optional {
+            location: String = Location,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Container for data which is to be inherited by other entries
+    XData("xdata"), new_xdata {
+        required {}
+        optional {
+            author: NameList = Author,
+            editor: NameList = Editor,
+            title: Text = Title,
+            main_title: Text = MainTitle,
+            journal_title: Text = JournalTitle,
+            book_title: Text = BookTitle,
+            date: Date = Date,
+            volume: String = Volume,
+            edition: String = Edition,
+            series: String = Series,
+            number: String = Number,
+            issue: String = Issue,
+            chapter: String = Chapter,
+            pages: Vec<Range> = Pages,
+            kind: String = Type,
+            organization: String = Organization,
+            institution: String = Institution,
+            publisher: String = Publisher,
+            location: String = Location,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Custom type for special bibliography styles
+    CustomA("customa"), new_custom_a {
+        required {}
+        optional {
+            author: NameList = Author,
+            editor: NameList = Editor,
+            title: Text = Title,
+            date: Date = Date,
+            kind: String = Type,
+            publisher: String = Publisher,
+            location: String = Location,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Custom type for special bibliography styles
+    CustomB("customb"), new_custom_b {
+        required {}
+        optional {
+            author: NameList = Author,
+            editor: NameList = Editor,
+            title: Text = Title,
+            date: Date = Date,
+            kind: String = Type,
+            publisher: String = Publisher,
+            location: String = Location,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Custom type for special bibliography styles
+    CustomC("customc"), new_custom_c {
+        required {}
+        optional {
+            author: NameList = Author,
+            editor: NameList = Editor,
+            title: Text = Title,
+            date: Date = Date,
+            kind: String = Type,
+            publisher: String = Publisher,
+            location: String = Location,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Custom type for special bibliography styles
+    CustomD("customd"), new_custom_d {
+        required {}
+        optional {
+            author: NameList = Author,
+            editor: NameList = Editor,
+            title: Text = Title,
+            date: Date = Date,
+            kind: String = Type,
+            publisher: String = Publisher,
+            location: String = Location,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Custom type for special bibliography styles
+    CustomE("custome"), new_custom_e {
+        required {}
+        optional {
+            author: NameList = Author,
+            editor: NameList = Editor,
+            title: Text = Title,
+            date: Date = Date,
+            kind: String = Type,
+            publisher: String = Publisher,
+            location: String = Location,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+
+    /// Custom type for special bibliography styles
+    CustomF("customf"), new_custom_f {
+        required {}
+        optional {
+            author: NameList = Author,
+            editor: NameList = Editor,
+            title: Text = Title,
+            date: Date = Date,
+            kind: String = Type,
+            publisher: String = Publisher,
+            location: String = Location,
+            pages: Vec<Range> = Pages,
+            doi: String = Doi,
+            eprint: String = Eprint,
+            url: String = Url,
+            url_date: Date = UrlDate,
+            orig_date: Date = OrigDate,
+        }
+        one_of {}
+    }
+}
@@ -1,20 +1,180 @@
 use std::fmt;
+use biblatex::{Date, Text};
 
 /// Field specifying some bibliographic information
 #[derive(Clone, PartialEq, Debug)]
 pub enum Field {
     Author(NameList),
     Editor(NameList),
-    Title(String),
-    JournalTitle(String),
-    BookTitle(String),
-    Year(i32),
+    Title(Text),
+    MainTitle(Text),
+    JournalTitle(Text),
+    BookTitle(Text),
+    Date(Date),
+    /// Date on which a URL was accessed
+    UrlDate(Date),
+    /// Date of the conference, for proceedings
+    EventDate(Date),
+    /// Date of the original edition of a reprinted work
+    OrigDate(Date),
     Pages(Vec<Range>),
     Url(String),
+    Doi(String),
+    Eprint(String),
     Volume(String),
     Series(String),
+    Number(String),
     Issue(String),
-    Unknown(String),
+    Edition(String),
+    Chapter(String),
+    Publisher(String),
+    Location(String),
+    Institution(String),
+    Organization(String),
+    /// The `type` field, e.g. `phdthesis` for a `@thesis`
+    Type(String),
+    /// Comma-separated list of the cite keys making up an `@set`
+    EntrySet(String),
+    /// Field which is not modelled, given by its name and value
+    Unknown(String, String),
+}
+
+impl Field {
+    /// Name of the field, e.g. `journaltitle` for `Field::JournalTitle`
+    pub fn name(&self) -> &str {
+        match *self {
+            Field::Author(_) => "author",
+            Field::Editor(_) => "editor",
+            Field::Title(_) => "title",
+            Field::MainTitle(_) => "maintitle",
+            Field::JournalTitle(_) => "journaltitle",
+            Field::BookTitle(_) => "booktitle",
+            Field::Date(_) => "date",
+            Field::UrlDate(_) => "urldate",
+            Field::EventDate(_) => "eventdate",
+            Field::OrigDate(_) => "origdate",
+            Field::Pages(_) => "pages",
+            Field::Url(_) => "url",
+            Field::Doi(_) => "doi",
+            Field::Eprint(_) => "eprint",
+            Field::Volume(_) => "volume",
+            Field::Series(_) => "series",
+            Field::Number(_) => "number",
+            Field::Issue(_) => "issue",
+            Field::Edition(_) => "edition",
+            Field::Chapter(_) => "chapter",
+            Field::Publisher(_) => "publisher",
+            Field::Location(_) => "location",
+            Field::Institution(_) => "institution",
+            Field::Organization(_) => "organization",
+            Field::Type(_) => "type",
+            Field::EntrySet(_) => "entryset",
+            Field::Unknown(ref name, _) => name,
+        }
+    }
+}
+
+/// Fields of an entry which are not modelled by its type, in input order
+///
+/// Names are stored in lowercase, values as they were given between the
+/// delimiters, after macro expansion.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct ExtraFields {
+    fields: Vec<(String, String)>,
+}
+
+impl ExtraFields {
+    pub fn new() -> ExtraFields {
+        ExtraFields::default()
+    }
+
+    /// Value of the field called `name`, if any
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        self.fields.iter().find(|f| f.0 == name).map(|f| f.1.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Sets the value of a field, returning its previous value
+    ///
+    /// A field which is already present keeps its position, new fields are
+    /// appended.
+    pub fn insert(&mut self, name: &str, value: &str) -> Option<String> {
+        let name = name.to_lowercase();
+        match self.fields.iter_mut().find(|f| f.0 == name) {
+            Some(f) => Some(::std::mem::replace(&mut f.1, value.to_string())),
+            None => {
+                self.fields.push((name, value.to_string()));
+                None
+            }
+        }
+    }
+
+    /// Removes a field, returning its value
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let name = name.to_lowercase();
+        let index = self.fields.iter().position(|f| f.0 == name)?;
+        Some(self.fields.remove(index).1)
+    }
+
+    /// Iterates over the names and values of the fields, in order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields.iter().map(|f| (f.0.as_str(), f.1.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+/// Names under which the fields of an entry were given in the input, for
+/// those whose names differ from the BibLaTeX ones, e.g. `journal` for
+/// `journaltitle`
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct OriginalNames {
+    names: Vec<(String, String)>,
+}
+
+impl OriginalNames {
+    pub fn new() -> OriginalNames {
+        OriginalNames::default()
+    }
+
+    /// Name under which the field called `name` in BibLaTeX was given, if it
+    /// differs
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.names.iter().find(|n| n.0 == name).map(|n| n.1.as_str())
+    }
+
+    /// Records the name under which a field was given, returning the name
+    /// previously recorded
+    pub fn insert(&mut self, name: &str, original: &str) -> Option<String> {
+        let previous = self.remove(name);
+        self.names.push((name.to_string(), original.to_string()));
+        previous
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let index = self.names.iter().position(|n| n.0 == name)?;
+        Some(self.names.remove(index).1)
+    }
+
+    /// Iterates over the BibLaTeX names of the fields along with the names
+    /// they were given under
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.names.iter().map(|n| (n.0.as_str(), n.1.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
 }
 
 /// Range type, typically used by the `pages` field
@@ -29,7 +189,13 @@ pub struct Range {
 pub struct Name {
     pub family: String,
     pub given: Option<String>,
-    // TODO: deal with prefixes/suffixes
+    /// The "von" part, e.g. `van` in `Ludwig van Beethoven`
+    pub prefix: Option<String>,
+    /// The "Jr" part, e.g. `Jr.` in `Smith, Jr., John`
+    pub suffix: Option<String>,
+    /// Per-name options given in the extended name format, such as
+    /// `useprefix=true`, in order
+    pub options: Vec<(String, String)>,
 }
 
 /// Name list type, typically used by the `author` or `editor` field
@@ -42,10 +208,10 @@ pub struct NameList {
 }
 
 impl NameList {
+    /// List of names which is not truncated
     pub fn from_names(names: Vec<Name>) -> NameList {
-        // TODO: check if last name is "others", set `truncated`
         NameList {
-            names: names,
+            names,
             truncated: false,
         }
     }
@@ -63,6 +229,11 @@ impl fmt::Display for NameList {
         for name in &self.names {
             names.push(name.to_string());
         }
-        write!(f, "{}", names.join(", "))
+        write!(f, "{}", names.join(", "))?;
+        match (self.truncated, self.names.is_empty()) {
+            (true, true) => write!(f, "et al."),
+            (true, false) => write!(f, " et al."),
+            _ => Ok(()),
+        }
     }
 }
\ No newline at end of file
@@ -1,5 +1,18 @@
-pub use biblatex::fields::{Field, Range, Name, NameList};
-pub use biblatex::entries::{Entry, InProceedings, Article};
+pub use biblatex::bibliography::Bibliography;
+pub use biblatex::fields::{Field, Range, Name, NameList, ExtraFields, OriginalNames};
+pub use biblatex::text::{Text, Chunk, Case};
+pub use biblatex::date::{Date, DatePart, RangeEnd, Season, Unspecified};
+pub use biblatex::entries::{
+    Entry, InvalidEntry,
+    Article, Book, MvBook, InBook, BookInBook, SuppBook, Booklet, Collection,
+    MvCollection, InCollection, SuppCollection, Dataset, Manual, Misc, Online, Patent,
+    Periodical, SuppPeriodical, Proceedings, MvProceedings, InProceedings, Reference,
+    MvReference, InReference, Report, Set, Software, Thesis, Unpublished, XData,
+    CustomA, CustomB, CustomC, CustomD, CustomE, CustomF,
+};
 
 mod fields;
+mod text;
+mod date;
 mod entries;
+mod bibliography;
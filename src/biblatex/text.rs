@@ -0,0 +1,321 @@
+use std::fmt;
+use latex::flatten;
+
+/// Part of a piece of rich text
+#[derive(Clone, Debug, PartialEq)]
+pub enum Chunk {
+    /// Text whose case may be changed; special characters, i.e. braced
+    /// groups starting with a command such as `{\'E}`, are kept in it as
+    /// they are, since their case is that of the surrounding text
+    Normal(String),
+    /// Text which was enclosed in braces to keep its case, without those
+    /// braces, e.g. `DNA` for `{DNA}`
+    Protected(String),
+    /// Math, without its `$` delimiters
+    Math(String),
+}
+
+/// Text of a title-like field, which records the spans protected by braces
+/// and the math in it
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Text {
+    pub chunks: Vec<Chunk>,
+}
+
+impl Text {
+    pub fn new(chunks: Vec<Chunk>) -> Text {
+        Text { chunks }
+    }
+
+    /// The text without any braces, and with math kept between `$` signs;
+    /// escaped braces and `$` signs outside of math are unescaped
+    pub fn plain(&self) -> String {
+        self.chunks.iter().map(|chunk| match *chunk {
+            Chunk::Normal(ref s) | Chunk::Protected(ref s) => flatten(s),
+            Chunk::Math(ref s) => format!("${}$", s),
+        }).collect()
+    }
+
+    /// The text as it would be written in a field value, braces included
+    pub fn to_latex(&self) -> String {
+        self.chunks.iter().map(|chunk| match *chunk {
+            Chunk::Normal(ref s) => s.clone(),
+            Chunk::Protected(ref s) => format!("{{{}}}", s),
+            Chunk::Math(ref s) => format!("${}$", s),
+        }).collect()
+    }
+}
+
+/// Case conversions, as done by BibTeX's `change.case$` or by title casing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Case {
+    /// Everything in lowercase (`change.case$` with `"l"`)
+    Lower,
+    /// Everything in uppercase (`change.case$` with `"u"`)
+    Upper,
+    /// Everything in lowercase except for the first character and the first
+    /// one after a colon and whitespace, which are left alone
+    /// (`change.case$` with `"t"`)
+    Sentence,
+    /// English title case: the first letter of each word is capitalised,
+    /// except for articles, conjunctions and short prepositions, which are
+    /// put in lowercase unless they start the title or a subtitle, or end it
+    Title,
+}
+
+// words which are not capitalised in title case
+const STOP_WORDS: [&str; 32] = [
+    "a", "an", "the", "and", "but", "or", "nor", "for", "so", "yet", "as",
+    "at", "by", "in", "of", "off", "on", "per", "to", "up", "via", "vs",
+    "v", "with", "from", "into", "onto", "over", "than", "upon", "if", "en",
+];
+
+// commands for special characters whose name changes with their case
+const CASED_COMMANDS: [&str; 10] = ["oe", "OE", "ae", "AE", "aa", "AA", "o", "O", "l", "L"];
+
+// unit of text, as far as changing its case is concerned
+enum Unit<'a> {
+    Char(char),
+    /// Contents of a special character, e.g. `\'E` for `{\'E}`
+    Special(&'a str),
+    /// Command or braced group outside of a special character, which is
+    /// kept as it is
+    Verbatim(&'a str),
+    /// Chunk which is kept as it is
+    Opaque(&'a Chunk),
+}
+
+impl<'a> Unit<'a> {
+    fn is_space(&self) -> bool {
+        match *self {
+            Unit::Char(c) => c.is_whitespace(),
+            _ => false,
+        }
+    }
+}
+
+// position of the brace closing the group opened at `start`
+fn group_end(s: &str, start: usize) -> usize {
+    let mut depth = 0;
+    for (i, c) in s[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return start + i;
+                }
+            }
+            _ => (),
+        }
+    }
+    s.len()
+}
+
+fn units(chunks: &[Chunk]) -> Vec<Unit<'_>> {
+    let mut units = Vec::new();
+    for chunk in chunks {
+        let s = match *chunk {
+            Chunk::Normal(ref s) => s,
+            _ => {
+                units.push(Unit::Opaque(chunk));
+                continue;
+            }
+        };
+        let mut i = 0;
+        while let Some(c) = s[i..].chars().next() {
+            let end = match c {
+                '{' => (group_end(s, i) + 1).min(s.len()),
+                // control words keep their name, control symbols their
+                // character
+                '\\' => match s[i + 1..].chars().next() {
+                    Some(next) if next.is_ascii_alphabetic() => {
+                        i + 1 + s[i + 1..].chars().take_while(char::is_ascii_alphabetic).count()
+                    }
+                    Some(next) => i + 1 + next.len_utf8(),
+                    None => i + 1,
+                },
+                _ => i + c.len_utf8(),
+            };
+            units.push(match c {
+                '{' if s[i + 1..].starts_with('\\') && s[..end].ends_with('}') => Unit::Special(&s[i + 1..end - 1]),
+                '{' | '\\' => Unit::Verbatim(&s[i..end]),
+                _ => Unit::Char(c),
+            });
+            i = end;
+        }
+    }
+    units
+}
+
+// converts a special character, given what is between its braces: the
+// letters are converted, as are the names of commands such as `\AE`, while
+// other commands are kept
+fn special(inner: &str, upper: bool) -> String {
+    let mut out = String::new();
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push_str(&convert(c, upper));
+            continue;
+        }
+        let mut name = String::new();
+        while let Some(&next) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+            name.push(next);
+            chars.next();
+        }
+        match name.as_str() {
+            "" => {
+                out.push('\\');
+                out.extend(chars.next());
+            }
+            "i" | "j" | "ss" if upper => out.push_str(&name.to_uppercase()),
+            n if CASED_COMMANDS.contains(&n) => {
+                out.push('\\');
+                out.push_str(&convert_str(n, upper));
+            }
+            n => {
+                out.push('\\');
+                out.push_str(n);
+            }
+        }
+    }
+    out
+}
+
+fn convert(c: char, upper: bool) -> String {
+    match upper {
+        true => c.to_uppercase().collect(),
+        false => c.to_lowercase().collect(),
+    }
+}
+
+fn convert_str(s: &str, upper: bool) -> String {
+    match upper {
+        true => s.to_uppercase(),
+        false => s.to_lowercase(),
+    }
+}
+
+// converts a unit, leaving anything other than letters as it is
+fn convert_unit(unit: &Unit, upper: bool, chunks: &mut Vec<Chunk>) {
+    match *unit {
+        Unit::Char(c) => push(chunks, &convert(c, upper)),
+        Unit::Special(inner) => push(chunks, &format!("{{{}}}", special(inner, upper))),
+        _ => keep_unit(unit, chunks),
+    }
+}
+
+fn keep_unit(unit: &Unit, chunks: &mut Vec<Chunk>) {
+    match *unit {
+        Unit::Char(c) => push(chunks, &c.to_string()),
+        Unit::Special(inner) => push(chunks, &format!("{{{}}}", inner)),
+        Unit::Verbatim(s) => push(chunks, s),
+        Unit::Opaque(chunk) => chunks.push(chunk.clone()),
+    }
+}
+
+// appends normal text
+fn push(chunks: &mut Vec<Chunk>, s: &str) {
+    match chunks.last_mut() {
+        Some(&mut Chunk::Normal(ref mut last)) => last.push_str(s),
+        _ => chunks.push(Chunk::Normal(s.to_string())),
+    }
+}
+
+// plain text of a word, for looking it up among the stop words
+fn word_text(units: &[Unit]) -> String {
+    units.iter().filter_map(|u| match *u {
+        Unit::Char(c) if c.is_alphabetic() => Some(c.to_ascii_lowercase()),
+        _ => None,
+    }).collect()
+}
+
+// converts a word to title case, each part of a hyphenated word separately;
+// `capitalise` forces the first part to be capitalised
+fn title_word(word: &[Unit], capitalise: bool, chunks: &mut Vec<Chunk>) {
+    for (n, part) in word.split(|u| matches!(*u, Unit::Char('-'))).enumerate() {
+        if n > 0 {
+            push(chunks, "-");
+        }
+        let stop = !(capitalise && n == 0) && STOP_WORDS.contains(&word_text(part).as_str());
+        // the first letter of the part, ignoring e.g. quotes and parentheses
+        let first = part.iter().position(|u| match *u {
+            Unit::Char(c) => c.is_alphanumeric(),
+            _ => true,
+        });
+        for (i, unit) in part.iter().enumerate() {
+            match stop {
+                true => convert_unit(unit, false, chunks),
+                false if Some(i) == first => convert_unit(unit, true, chunks),
+                false => keep_unit(unit, chunks),
+            }
+        }
+    }
+}
+
+impl Text {
+    /// Changes the case of the text, leaving protected spans and math as
+    /// they are
+    ///
+    /// Special characters such as `{\'E}` follow the case of the text
+    /// around them, as they do in BibTeX. Unlike in BibTeX, the names of
+    /// commands outside of braces are never changed.
+    pub fn to_case(&self, case: Case) -> Text {
+        let units = units(&self.chunks);
+        let mut chunks = Vec::new();
+        match case {
+            Case::Lower | Case::Upper => for unit in &units {
+                convert_unit(unit, case == Case::Upper, &mut chunks);
+            },
+            Case::Sentence => {
+                let mut keep = true;
+                let mut colon = false;
+                for unit in &units {
+                    match *unit {
+                        Unit::Char(c) if c.is_whitespace() => keep |= colon,
+                        _ if keep => {
+                            keep_unit(unit, &mut chunks);
+                            keep = false;
+                            colon = matches!(*unit, Unit::Char(':'));
+                            continue;
+                        }
+                        _ => colon = matches!(*unit, Unit::Char(':')),
+                    }
+                    convert_unit(unit, false, &mut chunks);
+                }
+            }
+            Case::Title => {
+                let mut subtitle = true;
+                let mut i = 0;
+                while i < units.len() {
+                    let length = units[i..].iter().take_while(|u| !u.is_space()).count();
+                    if length == 0 {
+                        keep_unit(&units[i], &mut chunks);
+                        i += 1;
+                        continue;
+                    }
+                    let word = &units[i..i + length];
+                    let last = units[i + length..].iter().all(Unit::is_space);
+                    title_word(word, subtitle || last, &mut chunks);
+                    subtitle = matches!(word.last(), Some(&Unit::Char(':' | '.' | '?' | '!' | '—')));
+                    i += length;
+                }
+            }
+        }
+        Text::new(chunks)
+    }
+}
+
+/// Text without any markup
+impl From<&str> for Text {
+    fn from(s: &str) -> Text {
+        Text::new(vec![Chunk::Normal(s.to_string())])
+    }
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.plain())
+    }
+}
@@ -0,0 +1,161 @@
+use std::error;
+use std::fmt;
+use biblatex::{Date, InvalidEntry};
+
+/// Error encountered while parsing a `.bib` file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    /// Byte offset of the error in the input
+    pub offset: usize,
+    /// Line of the error, starting from 1
+    pub line: usize,
+    /// Column of the error in characters, starting from 1
+    pub column: usize,
+    /// Cite key of the entry in which the error occurred, if any
+    pub key: Option<String>,
+    // the line in which the error occurred, boxed to keep errors small
+    snippet: Box<str>,
+}
+
+/// Reason why parsing failed
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// Malformed input, along with a description of the problem
+    Syntax(String),
+    /// A brace which is never closed
+    UnterminatedBrace,
+    UnknownEntryType(String),
+    UndefinedMacro(String),
+    /// Field value which cannot be interpreted, such as a year which is not
+    /// a number
+    InvalidValue { field: String, value: String },
+    /// Entry which could not be instantiated, e.g. because it lacks some
+    /// required field
+    InvalidEntry(Box<InvalidEntry>),
+    /// Warning that the legacy `year` and `month` fields of an entry do not
+    /// agree with its `date` field, which takes precedence
+    ConflictingDate { date: Box<Date>, legacy: Box<Date> },
+    /// Entries which inherit from each other in a loop through `crossref` or
+    /// `xdata`, given by their keys
+    InheritanceCycle(Vec<String>),
+    /// Warning that an entry refers to an entry which does not exist, e.g.
+    /// in its `crossref` field
+    UndefinedEntry(String),
+    /// Cite key which an earlier entry already has, when duplicates are
+    /// treated as errors
+    DuplicateKey(String),
+    /// Warning that one of two entries with the same cite key was left out
+    DroppedDuplicate(String),
+    /// Cite key or alternative key in `ids` which already refers to another
+    /// entry, given along with that entry's key
+    AmbiguousKey { key: String, entry: String },
+    /// Warning that a field is not modelled by the type of its entry, e.g. a
+    /// `publisher` for an `@article`, and is kept as an extra field
+    UnexpectedField(String),
+}
+
+impl ErrorKind {
+    /// Whether this is only a warning, which does not stop anything from
+    /// being parsed
+    pub fn is_warning(&self) -> bool {
+        matches!(*self, ErrorKind::ConflictingDate { .. } | ErrorKind::UndefinedEntry(_) |
+                 ErrorKind::DroppedDuplicate(_) | ErrorKind::UnexpectedField(_))
+    }
+}
+
+impl Error {
+    /// Creates an error at the given byte offset in `input`
+    pub fn new(input: &[u8], offset: usize, key: Option<String>, kind: ErrorKind) -> Error {
+        let offset = offset.min(input.len());
+        let line_start = input[..offset].iter().rposition(|&c| c == b'\n').map_or(0, |i| i + 1);
+        let line_end = input[offset..].iter().position(|&c| c == b'\n').map_or(input.len(), |i| offset + i);
+        let line = input[..offset].iter().filter(|&&c| c == b'\n').count() + 1;
+        let column = String::from_utf8_lossy(&input[line_start..offset]).chars().count() + 1;
+        let snippet = String::from_utf8_lossy(&input[line_start..line_end]).trim_end().into();
+        Error { kind, offset, line, column, key, snippet }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::Syntax(ref message) => write!(f, "{}", message),
+            ErrorKind::UnterminatedBrace => write!(f, "unterminated brace"),
+            ErrorKind::UnknownEntryType(ref t) => write!(f, "unknown entry type `{}`", t),
+            ErrorKind::UndefinedMacro(ref name) => write!(f, "undefined macro `{}`", name),
+            ErrorKind::InvalidValue { ref field, ref value } => {
+                write!(f, "invalid value `{}` for field `{}`", value, field)
+            }
+            ErrorKind::InvalidEntry(ref e) => write!(f, "{}", e),
+            ErrorKind::ConflictingDate { ref date, ref legacy } => {
+                write!(f, "`year`/`month` ({}) disagree with `date` ({}), which is used instead", legacy, date)
+            }
+            ErrorKind::InheritanceCycle(ref keys) => {
+                write!(f, "entries inherit from each other in a cycle: {}", keys.join(" -> "))
+            }
+            ErrorKind::UndefinedEntry(ref key) => write!(f, "reference to undefined entry `{}`", key),
+            ErrorKind::DuplicateKey(ref key) => write!(f, "duplicate cite key `{}`", key),
+            ErrorKind::DroppedDuplicate(ref key) => {
+                write!(f, "duplicate cite key `{}`, so only one of its entries is kept", key)
+            }
+            ErrorKind::AmbiguousKey { ref key, ref entry } => {
+                write!(f, "key `{}` already refers to entry `{}`", key, entry)
+            }
+            ErrorKind::UnexpectedField(ref name) => {
+                write!(f, "field `{}` is not expected for this entry type, so it is kept as an extra field", name)
+            }
+        }
+    }
+}
+
+// renders the error along with the offending line, e.g.
+//
+// error: unknown entry type `nonsense` in entry `b`
+//  --> line 2, column 1
+//   |
+// 2 | @nonsense{b, title = {B}}
+//   | ^
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.kind.is_warning() {
+            true => "warning",
+            false => "error",
+        };
+        write!(f, "{}: {}", severity, self.kind)?;
+        if let Some(ref key) = self.key {
+            write!(f, " in entry `{}`", key)?;
+        }
+        let gutter = " ".repeat(self.line.to_string().len());
+        writeln!(f)?;
+        writeln!(f, "{}--> line {}, column {}", gutter, self.line, self.column)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", self.line, self.snippet)?;
+        write!(f, "{} | {}^", gutter, " ".repeat(self.column - 1))
+    }
+}
+
+impl error::Error for Error {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn location() {
+        let input = b"@article{a,\n  year = {n.d.},\n}";
+        let kind = ErrorKind::InvalidValue {
+            field: "year".to_string(),
+            value: "n.d.".to_string(),
+        };
+        let error = Error::new(input, 21, Some("a".to_string()), kind);
+        assert_eq!((error.line, error.column), (2, 10));
+
+        let expected = "error: invalid value `n.d.` for field `year` in entry `a`
+ --> line 2, column 10
+  |
+2 |   year = {n.d.},
+  |          ^";
+        assert_eq!(error.to_string(), expected);
+    }
+}
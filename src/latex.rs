@@ -0,0 +1,414 @@
+// Conversion between LaTeX markup, as commonly found in `.bib` files, and
+// Unicode. Only text mode is handled: math (`$...$`) and commands which are
+// not known are kept as they are.
+
+// letters which each accent can be combined with, followed by the
+// precomposed characters, in the same order; other letters get a combining
+// character instead
+const ACCENTS: [(&str, &str, &str, char); 16] = [
+    ("'", "AEIOUYaeiouyCcNnSsZzLlRrGgKkMmPpWw", "ÁÉÍÓÚÝáéíóúýĆćŃńŚśŹźĹĺŔŕǴǵḰḱḾḿṔṕẂẃ", '\u{301}'),
+    ("`", "AEIOUaeiouNnWwYy", "ÀÈÌÒÙàèìòùǸǹẀẁỲỳ", '\u{300}'),
+    ("^", "AEIOUaeiouCcGgHhJjSsWwYy", "ÂÊÎÔÛâêîôûĈĉĜĝĤĥĴĵŜŝŴŵŶŷ", '\u{302}'),
+    ("\"", "AEIOUaeiouyYHhWwXxt", "ÄËÏÖÜäëïöüÿŸḦḧẄẅẌẍẗ", '\u{308}'),
+    ("~", "ANOanoIiUuEeYy", "ÃÑÕãñõĨĩŨũẼẽỸỹ", '\u{303}'),
+    ("=", "AEIOUaeiouYyGg", "ĀĒĪŌŪāēīōūȲȳḠḡ", '\u{304}'),
+    (".", "CEGIZcegzABDFHMNPRSTWXYabdfhmnprstwxy", "ĊĖĠİŻċėġżȦḂḊḞḢṀṄṖṘṠṪẆẊẎȧḃḋḟḣṁṅṗṙṡṫẇẋẏ", '\u{307}'),
+    ("u", "AEGIOUaegiou", "ĂĔĞĬŎŬăĕğĭŏŭ", '\u{306}'),
+    ("v", "CDENRSTZcdenrstzAaIiOoUuGgKkjHh", "ČĎĚŇŘŠŤŽčďěňřšťžǍǎǏǐǑǒǓǔǦǧǨǩǰȞȟ", '\u{30C}'),
+    ("H", "OUou", "ŐŰőű", '\u{30B}'),
+    ("c", "CGKLNRSTcgklnrstEe", "ÇĢĶĻŅŖŞŢçģķļņŗşţȨȩ", '\u{327}'),
+    ("k", "AEIUaeiuOo", "ĄĘĮŲąęįųǪǫ", '\u{328}'),
+    ("r", "AaUuwy", "ÅåŮůẘẙ", '\u{30A}'),
+    ("d", "AaEeIiOoUuYyBbDdHhKkLlMmNnRrSsTtVvWwZz", "ẠạẸẹỊịỌọỤụỴỵḄḅḌḍḤḥḲḳḶḷṂṃṆṇṚṛṢṣṬṭṾṿẈẉẒẓ", '\u{323}'),
+    ("b", "", "", '\u{331}'),
+    ("t", "", "", '\u{361}'),
+];
+
+// commands standing for a single character
+const SYMBOLS: [(&str, &str); 53] = [
+    ("ss", "ß"), ("SS", "SS"), ("ae", "æ"), ("AE", "Æ"), ("oe", "œ"), ("OE", "Œ"),
+    ("aa", "å"), ("AA", "Å"), ("o", "ø"), ("O", "Ø"), ("l", "ł"), ("L", "Ł"),
+    ("i", "ı"), ("j", "ȷ"), ("dh", "ð"), ("DH", "Ð"), ("th", "þ"), ("TH", "Þ"),
+    ("ng", "ŋ"), ("NG", "Ŋ"), ("dj", "đ"), ("DJ", "Đ"),
+    ("textendash", "–"), ("textemdash", "—"), ("dots", "…"), ("ldots", "…"),
+    ("textellipsis", "…"), ("S", "§"), ("P", "¶"), ("copyright", "©"),
+    ("textcopyright", "©"), ("pounds", "£"), ("textsterling", "£"), ("texteuro", "€"),
+    ("textregistered", "®"), ("texttrademark", "™"), ("textdegree", "°"),
+    ("dag", "†"), ("ddag", "‡"), ("textquoteleft", "‘"), ("textquoteright", "’"),
+    ("textquotedblleft", "“"), ("textquotedblright", "”"), ("guillemotleft", "«"),
+    ("guillemotright", "»"), ("textexclamdown", "¡"), ("textquestiondown", "¿"),
+    ("textbackslash", "\\"), ("textasciitilde", "~"), ("textunderscore", "_"),
+    ("textbullet", "•"), ("textperiodcentered", "·"), ("textasciicircum", "^"),
+];
+
+// control symbols, i.e. a backslash followed by a single non-letter
+const CONTROL_SYMBOLS: [(char, &str); 11] = [
+    ('&', "&"), ('%', "%"), ('$', "$"), ('#', "#"), ('_', "_"), ('{', "{"),
+    ('}', "}"), (' ', " "), (',', "\u{2009}"), ('-', ""), ('/', ""),
+];
+
+fn accent(name: &str) -> Option<&'static (&'static str, &'static str, &'static str, char)> {
+    ACCENTS.iter().find(|a| a.0 == name)
+}
+
+// applies an accent to a letter
+fn compose(accent: &(&str, &str, &str, char), base: &str) -> String {
+    // `\'{\i}` is written with a dotless i so that it doesn't get two dots
+    let base = match base {
+        "ı" => "i",
+        "ȷ" => "j",
+        _ => base,
+    };
+    let mut chars = base.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if let Some(i) = accent.1.chars().position(|b| b == c) {
+            return accent.2.chars().nth(i).unwrap().to_string();
+        }
+    }
+    format!("{}{}", base, accent.3)
+}
+
+// position of the brace closing the group opened at `start`
+fn group_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, &c) in chars.iter().enumerate().skip(start) {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+// characters whose escapes are kept by `decode_value`
+const STRUCTURAL: [char; 3] = ['$', '{', '}'];
+
+struct Decoder {
+    chars: Vec<char>,
+    pos: usize,
+    // whether to keep the escapes of the structural characters
+    keep_escapes: bool,
+}
+
+impl Decoder {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    // decodes up to the given position
+    fn decode(&mut self, end: usize) -> String {
+        let mut out = String::new();
+        while self.pos < end {
+            let c = self.chars[self.pos];
+            let rest = &self.chars[self.pos..end];
+            match c {
+                '\\' => out.push_str(&self.command()),
+                '$' => {
+                    // math is kept as it is
+                    let close = rest.iter().skip(1).position(|&c| c == '$').map_or(end, |i| self.pos + i + 2);
+                    out.extend(&self.chars[self.pos..close]);
+                    self.pos = close;
+                }
+                '{' => out.push_str(&self.group()),
+                '-' if rest.starts_with(&['-', '-', '-']) => {
+                    out.push('—');
+                    self.pos += 3;
+                }
+                '-' if rest.starts_with(&['-', '-']) => {
+                    out.push('–');
+                    self.pos += 2;
+                }
+                '`' if rest.starts_with(&['`', '`']) => {
+                    out.push('“');
+                    self.pos += 2;
+                }
+                '\'' if rest.starts_with(&['\'', '\'']) => {
+                    out.push('”');
+                    self.pos += 2;
+                }
+                '~' => {
+                    out.push('\u{a0}');
+                    self.pos += 1;
+                }
+                _ => {
+                    out.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        out
+    }
+
+    // braced group; the braces around a lone special character, such as
+    // `{\'e}`, are dropped along with the command
+    fn group(&mut self) -> String {
+        let end = match group_end(&self.chars, self.pos) {
+            Some(end) => end,
+            None => {
+                self.pos += 1;
+                return "{".to_string();
+            }
+        };
+        let special = self.chars.get(self.pos + 1) == Some(&'\\');
+        self.pos += 1;
+        let inner = self.decode(end);
+        self.pos = end + 1;
+        match special && !inner.contains('\\') && !inner.contains('{') {
+            true => inner,
+            false => format!("{{{}}}", inner),
+        }
+    }
+
+    // argument of an accent: a braced group or a single character, either
+    // of which may be a command such as `\i`
+    fn argument(&mut self) -> Option<String> {
+        self.skip_spaces();
+        match self.peek()? {
+            '{' => {
+                let end = group_end(&self.chars, self.pos)?;
+                self.pos += 1;
+                let inner = self.decode(end);
+                self.pos = end + 1;
+                Some(inner)
+            }
+            '\\' => Some(self.command()),
+            '}' => None,
+            c => {
+                self.pos += 1;
+                Some(c.to_string())
+            }
+        }
+    }
+
+    fn command(&mut self) -> String {
+        let start = self.pos;
+        self.pos += 1;
+        let next = match self.peek() {
+            Some(c) => c,
+            None => return "\\".to_string(),
+        };
+        let name: String = match next.is_ascii_alphabetic() {
+            true => self.chars[self.pos..].iter().take_while(|c| c.is_ascii_alphabetic()).collect(),
+            false => next.to_string(),
+        };
+        self.pos += name.chars().count();
+
+        if let Some(accent) = accent(&name) {
+            let before = self.pos;
+            match self.argument() {
+                Some(base) if !base.contains('\\') => return compose(accent, &base),
+                _ => {
+                    self.pos = before;
+                    return self.chars[start..self.pos].iter().collect();
+                }
+            }
+        }
+        if next.is_ascii_alphabetic() {
+            if let Some(&(_, symbol)) = SYMBOLS.iter().find(|s| s.0 == name) {
+                // spaces after a control word and an empty group, as in
+                // `\ss{}`, only serve to end it
+                self.skip_spaces();
+                if self.chars[self.pos..].starts_with(&['{', '}']) {
+                    self.pos += 2;
+                }
+                return symbol.to_string();
+            }
+        } else if self.keep_escapes && STRUCTURAL.contains(&next) {
+            return format!("\\{}", next);
+        } else if let Some(&(_, symbol)) = CONTROL_SYMBOLS.iter().find(|s| s.0 == next) {
+            return symbol.to_string();
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+}
+
+/// Decodes the LaTeX accents, special characters, dashes, quotes and ties in
+/// some text into Unicode
+///
+/// Commands which are not known, and math, are kept as they are, as are any
+/// braces other than those around a special character such as `{\'e}`.
+pub fn decode(text: &str) -> String {
+    let mut decoder = Decoder { chars: text.chars().collect(), pos: 0, keep_escapes: false };
+    let end = decoder.chars.len();
+    decoder.decode(end)
+}
+
+/// Decodes a field value like `decode`, except that `\$`, `\{` and `\}` are
+/// kept escaped, so that they can still be told apart from the math and
+/// braces which structure the value
+pub fn decode_value(text: &str) -> String {
+    let mut decoder = Decoder { chars: text.chars().collect(), pos: 0, keep_escapes: true };
+    let end = decoder.chars.len();
+    decoder.decode(end)
+}
+
+// removes the braces from some LaTeX, such as the contents of a literal;
+// escaped braces and `$` signs become the characters themselves
+pub(crate) fn flatten(s: &str) -> String {
+    let mut flat = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next)) if "${}".contains(next) => {
+                flat.push(next);
+                chars.next();
+            }
+            ('\\', Some(&next)) => {
+                flat.push(c);
+                flat.push(next);
+                chars.next();
+            }
+            ('{', _) | ('}', _) => (),
+            _ => flat.push(c),
+        }
+    }
+    flat
+}
+
+/// What to do with characters which cannot be written as LaTeX
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fallback {
+    /// Keep the character as it is
+    Keep,
+    /// Write this text in its place
+    Replace(String),
+    /// Leave the character out
+    Drop,
+}
+
+// LaTeX for a character which is not ASCII, if there is any
+fn encode_char(c: char) -> Option<String> {
+    let text = match c {
+        '\u{a0}' => "~",
+        '–' => "--",
+        '—' => "---",
+        '“' => "``",
+        '”' => "''",
+        '‘' => "`",
+        '’' => "'",
+        'ı' => "{\\i}",
+        'ȷ' => "{\\j}",
+        _ => "",
+    };
+    if !text.is_empty() {
+        return Some(text.to_string());
+    }
+    for accent in ACCENTS.iter() {
+        if let Some(i) = accent.2.chars().position(|p| p == c) {
+            return Some(with_accent(accent.0, accent.1.chars().nth(i).unwrap()));
+        }
+    }
+    SYMBOLS.iter().find(|s| s.1.chars().eq(Some(c))).map(|s| format!("{{\\{}}}", s.0))
+}
+
+// special character for a letter with an accent, braced as BibTeX expects
+// it to be for sorting and case changes, e.g. `{\'e}` or `{\c{c}}`
+fn with_accent(accent: &str, letter: char) -> String {
+    match accent.chars().all(|c| c.is_ascii_alphabetic()) {
+        true => format!("{{\\{}{{{}}}}}", accent, letter),
+        false => format!("{{\\{}{}}}", accent, letter),
+    }
+}
+
+/// Encodes the characters in some text which are not ASCII as LaTeX, e.g.
+/// `é` as `{\'e}`, so that it can be processed by classic BibTeX
+///
+/// Letters followed by combining accents are encoded too, while characters
+/// which have no LaTeX equivalent are handled according to `fallback`.
+pub fn encode(text: &str, fallback: &Fallback) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let combining = chars.peek().and_then(|&m| ACCENTS.iter().find(|a| a.3 == m));
+        if let (true, Some(accent)) = (c.is_ascii_alphabetic(), combining) {
+            chars.next();
+            out.push_str(&with_accent(accent.0, c));
+        } else if c.is_ascii() {
+            out.push(c);
+        } else if let Some(encoded) = encode_char(c) {
+            out.push_str(&encoded);
+        } else {
+            match *fallback {
+                Fallback::Keep => out.push(c),
+                Fallback::Replace(ref replacement) => out.push_str(replacement),
+                Fallback::Drop => (),
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tables() {
+        for accent in ACCENTS.iter() {
+            assert_eq!(accent.1.chars().count(), accent.2.chars().count(), "accent {}", accent.0);
+        }
+    }
+
+    #[test]
+    fn accents() {
+        assert_eq!(decode("G{\\\"o}del"), "Gödel");
+        assert_eq!(decode("{\\'E}cole"), "École");
+        assert_eq!(decode("Fran\\c{c}ois"), "François");
+        assert_eq!(decode("Fran\\c cois"), "François");
+        assert_eq!(decode("\\v{S}koda"), "Škoda");
+        assert_eq!(decode("Erd\\H{o}s"), "Erdős");
+        assert_eq!(decode("Ma\\'{\\i}a"), "Maía");
+        assert_eq!(decode("\\'{\\i}"), "í");
+        assert_eq!(decode("\\~n"), "ñ");
+        assert_eq!(decode("\\'x"), "x\u{301}");
+        assert_eq!(decode("\\k{a}"), "ą");
+    }
+
+    #[test]
+    fn symbols() {
+        assert_eq!(decode("Stra\\ss{}e"), "Straße");
+        assert_eq!(decode("Stra{\\ss}e"), "Straße");
+        assert_eq!(decode("\\AE sop"), "Æsop");
+        assert_eq!(decode("Pages 1--10, 1990---2000"), "Pages 1–10, 1990—2000");
+        assert_eq!(decode("D.~E. Knuth"), "D.\u{a0}E. Knuth");
+        assert_eq!(decode("Smith \\& Sons, 50\\%"), "Smith & Sons, 50%");
+        assert_eq!(decode("``Quoted''"), "“Quoted”");
+    }
+
+    #[test]
+    fn preserved() {
+        assert_eq!(decode("The {DNA} of \\emph{Drosophila}"), "The {DNA} of \\emph{Drosophila}");
+        assert_eq!(decode("$\\alpha$-{\\'e}l\\'ements--$x--y$"), "$\\alpha$-éléments–$x--y$");
+        assert_eq!(decode("\\unknown{x} \\"), "\\unknown{x} \\");
+        assert_eq!(decode("{\\relax \\'e}"), "{\\relax é}");
+        assert_eq!(decode_value("\\{x\\} costs \\$5 \\& {\\'e}"), "\\{x\\} costs \\$5 & é");
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(encode("Gödel, Erdős and François", &Fallback::Keep), "G{\\\"o}del, Erd{\\H{o}}s and Fran{\\c{c}}ois");
+        assert_eq!(encode("Straße 1–10 “Æsop”", &Fallback::Keep), "Stra{\\ss}e 1--10 ``{\\AE}sop''");
+        assert_eq!(encode("Mai\u{301}a", &Fallback::Keep), "Ma{\\'i}a");
+        assert_eq!(encode("α β", &Fallback::Keep), "α β");
+        assert_eq!(encode("α β", &Fallback::Replace("?".to_string())), "? ?");
+        assert_eq!(encode("α β", &Fallback::Drop), " ");
+
+        for accent in ACCENTS.iter() {
+            for c in accent.2.chars() {
+                assert_eq!(decode(&encode(&c.to_string(), &Fallback::Drop)), c.to_string());
+            }
+        }
+    }
+}
@@ -1,7 +1,11 @@
-pub use parser::parse_bib;
-pub use biblatex::{Entry, Name, NameList};
+pub use parser::{parse_bib, parse_bib_lenient, parse_bib_with, parse_bib_lenient_with, Options, Duplicates, Rules};
+pub use biblatex::{Bibliography, Entry, Name, NameList};
+pub use error::{Error, ErrorKind};
 
 extern crate pom;
 
 pub mod biblatex;
-pub mod parser;
\ No newline at end of file
+pub mod latex;
+pub mod parser;
+pub mod writer;
+mod error;
\ No newline at end of file
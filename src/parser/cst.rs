@@ -0,0 +1,650 @@
+use std::fmt;
+use pom::{DataInput, Input, Parser};
+use pom::parser::*;
+use pom::char_class::{alphanum, digit, multispace, space};
+use parser::{identifier, lower, resync, Options};
+use parser::entries::{tag, cite_key};
+use biblatex::Bibliography;
+use error::Error;
+
+// The concrete syntax tree keeps every byte of the input: each token carries
+// the whitespace preceding it, and text outside of any block is kept as
+// junk. Writing the tree back out therefore reproduces the input exactly,
+// and edits only touch the tokens they replace. The text of tokens is
+// decoded as UTF-8 with any invalid bytes replaced, while the document keeps
+// the input so that the original bytes are written back.
+
+/// Byte range of a token in the input
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Piece of text in the input, along with the whitespace preceding it
+///
+/// Bytes which are not valid UTF-8 show up as U+FFFD in the text. Tokens
+/// created by edits have an empty span at the position where they
+/// were inserted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub leading: String,
+    pub text: String,
+    pub span: Span,
+}
+
+impl Token {
+    fn inserted(leading: &str, text: &str, position: usize) -> Token {
+        Token {
+            leading: leading.to_string(),
+            text: text.to_string(),
+            span: Span { start: position, end: position },
+        }
+    }
+}
+
+/// Field value: pieces such as `{Some Title}`, `"text"`, `2004` or `jacm`,
+/// separated by `#` tokens
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValueNode {
+    pub tokens: Vec<Token>,
+}
+
+impl ValueNode {
+    /// The pieces making up the value, without the `#` separators
+    pub fn pieces(&self) -> impl Iterator<Item = &Token> {
+        self.tokens.iter().step_by(2)
+    }
+}
+
+/// `name = value`, followed by a comma unless it is the last field
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldNode {
+    pub name: Token,
+    pub equals: Token,
+    pub value: ValueNode,
+    pub comma: Option<Token>,
+}
+
+/// `@type{key, fields...}` or `@type(key, fields...)`
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntryNode {
+    /// Entry type, including the `@`
+    pub tag: Token,
+    /// `{` or `(`, matched by `close`
+    pub open: Token,
+    pub key: Token,
+    /// Comma after the key, which may be left out when there are no fields
+    pub comma: Option<Token>,
+    pub fields: Vec<FieldNode>,
+    pub close: Token,
+}
+
+/// `@string{name = value}`
+#[derive(Clone, Debug, PartialEq)]
+pub struct StringNode {
+    pub tag: Token,
+    pub open: Token,
+    pub name: Token,
+    pub equals: Token,
+    pub value: ValueNode,
+    pub close: Token,
+}
+
+/// `@preamble{value}`
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreambleNode {
+    pub tag: Token,
+    pub open: Token,
+    pub value: ValueNode,
+    pub close: Token,
+}
+
+/// Top-level block of a `.bib` file
+#[derive(Clone, Debug, PartialEq)]
+pub enum Item {
+    Entry(EntryNode),
+    String(StringNode),
+    Preamble(PreambleNode),
+    /// `@comment`, either with a braced block or up to the end of the line
+    Comment(Token),
+    /// Text outside of any block, which BibTeX ignores
+    Junk(Token),
+    /// Block which could not be parsed, up to the point where parsing resumed
+    Invalid(Token),
+}
+
+/// Lossless syntax tree of a `.bib` file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    pub items: Vec<Item>,
+    // the parsed input, which spans refer to
+    source: Vec<u8>,
+}
+
+impl Document {
+    /// Parses a `.bib` file; malformed blocks are kept as `Item::Invalid`,
+    /// from which parsing resumes at the next line starting with `@`
+    pub fn parse(buf: &[u8]) -> Document {
+        let parser = item();
+        let mut input = DataInput::new(buf);
+        let mut items = Vec::new();
+        while input.position < buf.len() {
+            match parser.parse(&mut input) {
+                Ok(item) => items.push(item),
+                Err(_) => {
+                    let start = input.position;
+                    let end = resync(buf, start);
+                    let text = String::from_utf8_lossy(&buf[start..end]);
+                    let leading = text.len() - text.trim_start().len();
+                    let token = Token {
+                        leading: text[..leading].to_string(),
+                        text: text[leading..].to_string(),
+                        span: Span { start: start + leading, end },
+                    };
+                    items.push(Item::Invalid(token));
+                    input.position = end;
+                }
+            }
+        }
+        Document { items, source: buf.to_vec() }
+    }
+
+    /// Writes the document back out; unlike `to_string`, this reproduces
+    /// input which is not valid UTF-8 byte for byte
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for token in self.items.iter().flat_map(Item::tokens) {
+            bytes.extend_from_slice(token.leading.as_bytes());
+            // tokens which were not edited are taken from the input
+            match self.source.get(token.span.start..token.span.end) {
+                Some(original) if String::from_utf8_lossy(original) == token.text => {
+                    bytes.extend_from_slice(original)
+                }
+                _ => bytes.extend_from_slice(token.text.as_bytes()),
+            }
+        }
+        bytes
+    }
+
+    /// Interprets the document, as `parse_bib_lenient` would its text
+    pub fn lower(&self) -> (Bibliography, Vec<Error>) {
+        self.lower_with(&Options::default())
+    }
+
+    /// Interprets the document with the given options
+    pub fn lower_with(&self, options: &Options) -> (Bibliography, Vec<Error>) {
+        let bytes = self.to_bytes();
+        // edits leave spans out of date, so positions are taken afresh
+        lower(&Document::parse(&bytes), &bytes, options)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &EntryNode> {
+        self.items.iter().filter_map(|item| match *item {
+            Item::Entry(ref entry) => Some(entry),
+            _ => None,
+        })
+    }
+
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = &mut EntryNode> {
+        self.items.iter_mut().filter_map(|item| match *item {
+            Item::Entry(ref mut entry) => Some(entry),
+            _ => None,
+        })
+    }
+
+    /// The entry with the given cite key, if any
+    pub fn entry(&self, key: &str) -> Option<&EntryNode> {
+        self.entries().find(|e| e.key() == key)
+    }
+
+    pub fn entry_mut(&mut self, key: &str) -> Option<&mut EntryNode> {
+        self.entries_mut().find(|e| e.key() == key)
+    }
+}
+
+impl EntryNode {
+    /// Entry type, in lowercase and without the `@`
+    pub fn entry_type(&self) -> String {
+        self.tag.text[1..].to_lowercase()
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key.text
+    }
+
+    /// The field with the given (case-insensitive) name, if any
+    pub fn field(&self, name: &str) -> Option<&FieldNode> {
+        self.fields.iter().find(|f| f.name.text.eq_ignore_ascii_case(name))
+    }
+
+    /// Sets the value of a field, which is delimited the same way as the
+    /// value it replaces (or the last field, for new fields)
+    ///
+    /// New fields are added at the end, laid out like the last field.
+    pub fn set_field(&mut self, name: &str, value: &str) {
+        if let Some(field) = self.fields.iter_mut().find(|f| f.name.text.eq_ignore_ascii_case(name)) {
+            let first = &field.value.tokens[0];
+            let text = delimit(value, &first.text);
+            field.value.tokens = vec![Token::inserted(&first.leading, &text, first.span.start)];
+            return;
+        }
+
+        let position = self.close.span.start;
+        let widths: Vec<usize> = self.fields.iter()
+            .map(|f| f.name.text.len() + f.equals.leading.len())
+            .collect();
+        let aligned = widths.len() > 1 && widths.iter().all(|&w| w == widths[0])
+            && self.fields.iter().all(|f| f.equals.leading.chars().all(|c| c == ' '));
+        let field = match self.fields.last_mut() {
+            Some(last) => {
+                // a trailing comma stays trailing
+                let comma = last.comma.clone();
+                if last.comma.is_none() {
+                    last.comma = Some(Token::inserted("", ",", position));
+                }
+                // keep the `=` signs aligned if they were
+                let padding = match aligned {
+                    true if widths[0] > name.len() => " ".repeat(widths[0] - name.len()),
+                    _ => last.equals.leading.clone(),
+                };
+                let first = &last.value.tokens[0];
+                FieldNode {
+                    name: Token::inserted(&last.name.leading, name, position),
+                    equals: Token::inserted(&padding, "=", position),
+                    value: ValueNode {
+                        tokens: vec![Token::inserted(&first.leading, &delimit(value, &first.text), position)],
+                    },
+                    comma: comma.map(|c| Token::inserted(&c.leading, ",", position)),
+                }
+            }
+            None => {
+                if self.comma.is_none() {
+                    self.comma = Some(Token::inserted("", ",", self.key.span.end));
+                }
+                FieldNode {
+                    name: Token::inserted("\n  ", name, position),
+                    equals: Token::inserted(" ", "=", position),
+                    value: ValueNode {
+                        tokens: vec![Token::inserted(" ", &delimit(value, "{"), position)],
+                    },
+                    comma: None,
+                }
+            }
+        };
+        if self.fields.is_empty() && !self.close.leading.contains('\n') {
+            self.close.leading.insert(0, '\n');
+        }
+        self.fields.push(field);
+    }
+
+    /// Removes a field, returning whether there was one
+    pub fn remove_field(&mut self, name: &str) -> bool {
+        match self.fields.iter().position(|f| f.name.text.eq_ignore_ascii_case(name)) {
+            Some(index) => {
+                self.fields.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// delimits a new value the same way as an existing piece
+fn delimit(value: &str, like: &str) -> String {
+    match like.starts_with('"') && !value.contains('"') {
+        true => format!("\"{}\"", value),
+        false => format!("{{{}}}", value),
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.leading, self.text)
+    }
+}
+
+impl fmt::Display for ValueNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "{}", token)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FieldNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}{}", self.name, self.equals, self.value)?;
+        if let Some(ref comma) = self.comma {
+            write!(f, "{}", comma)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for EntryNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}{}", self.tag, self.open, self.key)?;
+        if let Some(ref comma) = self.comma {
+            write!(f, "{}", comma)?;
+        }
+        for field in &self.fields {
+            write!(f, "{}", field)?;
+        }
+        write!(f, "{}", self.close)
+    }
+}
+
+impl Item {
+    // every token of the item, in order
+    fn tokens(&self) -> Vec<&Token> {
+        match *self {
+            Item::Entry(ref e) => {
+                let mut tokens = vec![&e.tag, &e.open, &e.key];
+                tokens.extend(&e.comma);
+                for field in &e.fields {
+                    tokens.extend(&[&field.name, &field.equals]);
+                    tokens.extend(&field.value.tokens);
+                    tokens.extend(&field.comma);
+                }
+                tokens.push(&e.close);
+                tokens
+            }
+            Item::String(ref s) => {
+                let mut tokens = vec![&s.tag, &s.open, &s.name, &s.equals];
+                tokens.extend(&s.value.tokens);
+                tokens.push(&s.close);
+                tokens
+            }
+            Item::Preamble(ref p) => {
+                let mut tokens = vec![&p.tag, &p.open];
+                tokens.extend(&p.value.tokens);
+                tokens.push(&p.close);
+                tokens
+            }
+            Item::Comment(ref t) | Item::Junk(ref t) | Item::Invalid(ref t) => vec![t],
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Item::Entry(ref e) => write!(f, "{}", e),
+            Item::String(ref s) => {
+                write!(f, "{}{}{}{}{}{}", s.tag, s.open, s.name, s.equals, s.value, s.close)
+            }
+            Item::Preamble(ref p) => write!(f, "{}{}{}{}", p.tag, p.open, p.value, p.close),
+            Item::Comment(ref t) | Item::Junk(ref t) | Item::Invalid(ref t) => write!(f, "{}", t),
+        }
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for item in &self.items {
+            write!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+// recognised text along with the whitespace preceding it
+fn token<T: 'static>(text: Parser<u8, T>) -> Parser<u8, Token> {
+    let leading = is_a(multispace).repeat(0..).convert(String::from_utf8);
+    let text = text.collect().map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+    let text = empty().pos() + text + empty().pos();
+    (leading + text).map(|(leading, ((start, text), end))| {
+        Token { leading, text, span: Span { start, end } }
+    })
+}
+
+// braced text, which may contain any bytes as long as the braces are balanced
+fn braced() -> Parser<u8, ()> {
+    (sym(b'{') * (none_of(b"{}").discard() | call(braced)).repeat(0..) - sym(b'}')).discard()
+}
+
+fn quoted() -> Parser<u8, ()> {
+    (sym(b'"') * (none_of(b"\"{}").discard() | call(braced)).repeat(0..) - sym(b'"')).discard()
+}
+
+// body of a block between `{` and `}`, or `(` and `)`
+fn delimited<T: 'static>(body: fn() -> Parser<u8, T>) -> Parser<u8, ((Token, T), Token)> {
+    let opening = is_a(multispace).repeat(0..) * one_of(b"{(");
+    let braces = token(sym(b'{')) + body() + token(sym(b'}'));
+    let parentheses = token(sym(b'(')) + body() + token(sym(b')'));
+    // the opening delimiter decides which one applies, so that errors are
+    // reported where the body goes wrong
+    Parser::new(move |input: &mut dyn Input<u8>| {
+        let start = input.position();
+        let open = opening.parse(input);
+        input.jump_to(start);
+        match open {
+            Ok(b'(') => parentheses.parse(input),
+            _ => braces.parse(input),
+        }
+    })
+}
+
+fn piece() -> Parser<u8, Token> {
+    let number = is_a(digit).repeat(1..).discard();
+    token(braced() | quoted() | number | identifier().discard())
+}
+
+pub fn value() -> Parser<u8, ValueNode> {
+    let concatenated = (token(sym(b'#')) + piece()).repeat(0..);
+    (piece() + concatenated).map(|(first, rest)| {
+        let mut tokens = vec![first];
+        for (hash, piece) in rest {
+            tokens.push(hash);
+            tokens.push(piece);
+        }
+        ValueNode { tokens }
+    })
+}
+
+pub fn field() -> Parser<u8, FieldNode> {
+    let field = token(identifier()) + token(sym(b'=')) + value();
+    field.map(|((name, equals), value)| FieldNode { name, equals, value, comma: None })
+}
+
+// the key and fields of an entry; the comma after the key is only needed
+// when some fields follow
+fn entry_body() -> Parser<u8, (Token, Option<Token>, Vec<FieldNode>)> {
+    let separated = (field() + token(sym(b','))).map(|(f, comma)| FieldNode { comma: Some(comma), .. f });
+    let last = field() + token(sym(b',')).opt();
+    let fields = separated.repeat(0..) + last.map(|(f, comma)| FieldNode { comma, .. f }).opt();
+    let fields = fields.map(|(mut fields, last)| {
+        fields.extend(last);
+        fields
+    });
+    (token(cite_key()) + (token(sym(b',')) + fields).opt()).map(|(key, rest)| match rest {
+        Some((comma, fields)) => (key, Some(comma), fields),
+        None => (key, None, vec![]),
+    })
+}
+
+pub fn entry() -> Parser<u8, EntryNode> {
+    let tag = token(sym(b'@') * is_a(alphanum).repeat(1..).discard());
+    (tag + delimited(entry_body)).map(|(tag, ((open, (key, comma, fields)), close))| {
+        EntryNode { tag, open, key, comma, fields, close }
+    })
+}
+
+pub fn string_definition() -> Parser<u8, StringNode> {
+    let definition = || token(identifier()) + token(sym(b'=')) + value();
+    (token(tag("string")) + delimited(definition)).map(|(tag, ((open, ((name, equals), value)), close))| {
+        StringNode { tag, open, name, equals, value, close }
+    })
+}
+
+pub fn preamble() -> Parser<u8, PreambleNode> {
+    (token(tag("preamble")) + delimited(value)).map(|(tag, ((open, value), close))| {
+        PreambleNode { tag, open, value, close }
+    })
+}
+
+// `@comment`, which either comments out a braced block or the rest of the line
+pub fn comment() -> Parser<u8, Token> {
+    let rest_of_line = none_of(b"\n").repeat(0..).discard();
+    token(tag("comment") * is_a(space).repeat(0..) * (braced() | rest_of_line))
+}
+
+// succeeds without consuming anything if only spaces precede it on its line
+fn line_start() -> Parser<u8, ()> {
+    Parser::new(|input: &mut dyn Input<u8>| {
+        let position = input.position();
+        let indented = (0..position).rev()
+            .map(|i| input.segment(i, i + 1)[0])
+            .take_while(|&c| c != b'\n')
+            .all(space);
+        match indented {
+            true => Ok(()),
+            false => Err(::pom::Error::Mismatch { message: "expected start of line".to_string(), position }),
+        }
+    })
+}
+
+// text outside of any block; lines starting with `%` are skipped entirely,
+// since they might contain an `@`, while a `%` elsewhere is just text
+fn junk() -> Parser<u8, Token> {
+    let comment_line = line_start() * sym(b'%') * none_of(b"\n").repeat(0..);
+    let text = (none_of(b"@%").discard() | (!line_start() * sym(b'%')).discard()).repeat(1..);
+    let trailing_space = empty() - end();
+    token((comment_line.discard() | text.discard()) | trailing_space)
+}
+
+fn item() -> Parser<u8, Item> {
+    string_definition().map(Item::String) |
+    preamble().map(Item::Preamble) |
+    comment().map(Item::Comment) |
+    entry().map(Item::Entry) |
+    junk().map(Item::Junk)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use biblatex::{Entry, Field};
+
+    const BIB: &[u8] = br#"% exported from somewhere
+@String{ tac = "Theory and Applications of Categories" }
+@preamble{ "\providecommand{\noop}[1]{}" }
+
+@Article{baez/article,
+  author       = {Baez, John C. and Lauda, Aaron D.},
+  title        = "Higher-Dimensional Algebra {V}: 2-Groups",
+  journaltitle = tac,
+  date         = 2004
+}
+@comment{jabref-meta: databaseType:biblatex;}
+@misc{broken, title = {B} junk}
+@misc{notes, editor = {Doe, Jane}, title = {Notes}, year = 2010, }
+"#;
+
+    #[test]
+    fn round_trip() {
+        let document = Document::parse(BIB);
+        assert_eq!(document.to_string().as_bytes(), BIB);
+
+        let kinds: Vec<&str> = document.items.iter().map(|item| match *item {
+            Item::Entry(_) => "entry",
+            Item::String(_) => "string",
+            Item::Preamble(_) => "preamble",
+            Item::Comment(_) => "comment",
+            Item::Junk(_) => "junk",
+            Item::Invalid(_) => "invalid",
+        }).collect();
+        assert_eq!(kinds, vec!["junk", "string", "preamble", "entry", "comment", "invalid", "entry", "junk"]);
+    }
+
+    #[test]
+    fn spans() {
+        let document = Document::parse(BIB);
+        let entry = document.entry("baez/article").unwrap();
+        assert_eq!(entry.entry_type(), "article");
+        let title = entry.field("TITLE").unwrap();
+        let span = title.value.tokens[0].span;
+        assert_eq!(&BIB[span.start..span.end], &b"\"Higher-Dimensional Algebra {V}: 2-Groups\""[..]);
+        assert_eq!(title.value.tokens[0].leading, " ");
+        assert_eq!(entry.tag.text, "@Article");
+    }
+
+    #[test]
+    fn edits() {
+        let mut document = Document::parse(BIB);
+        {
+            let entry = document.entry_mut("baez/article").unwrap();
+            entry.set_field("doi", "10.1000/xyz");
+            entry.set_field("title", "Higher-Dimensional Algebra V");
+        }
+        document.entry_mut("notes").unwrap().set_field("url", "https://example.org");
+        assert!(document.entry_mut("notes").unwrap().remove_field("editor"));
+
+        let expected = String::from_utf8(BIB.to_vec()).unwrap()
+            .replace("\"Higher-Dimensional Algebra {V}: 2-Groups\"", "\"Higher-Dimensional Algebra V\"")
+            .replace("date         = 2004\n", "date         = 2004,\n  doi          = {10.1000/xyz}\n")
+            .replace(" editor = {Doe, Jane},", "")
+            .replace("year = 2010, ", "year = 2010, url = {https://example.org}, ");
+        assert_eq!(document.to_string(), expected);
+
+        let (bibliography, errors) = document.lower();
+        assert_eq!(bibliography.len(), 1);
+        assert!(bibliography.entries()[0].1.fields().contains(&Field::Doi("10.1000/xyz".to_string())));
+        assert_eq!(bibliography.entries()[0].1.title().unwrap().plain(), "Higher-Dimensional Algebra V");
+        // `broken` is malformed, `notes` lost its editor
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[1].key, Some("notes".to_string()));
+    }
+
+    #[test]
+    fn empty_entry() {
+        let mut document = Document::parse(b"@misc{key,}");
+        document.entry_mut("key").unwrap().set_field("title", "T");
+        assert_eq!(document.to_string(), "@misc{key,\n  title = {T}\n}");
+    }
+
+    #[test]
+    fn delimiters() {
+        let raw = b"@misc(a, editor = {A}, title = {(A)}, date = 2001)\n@String(b = {B})\n@misc{c}\n@misc( d )";
+        let document = Document::parse(raw);
+        assert_eq!(document.to_string().as_bytes(), &raw[..]);
+        assert_eq!(document.entries().map(EntryNode::key).collect::<Vec<_>>(), vec!["a", "c", "d"]);
+        assert_eq!(document.entry("a").unwrap().close.text, ")");
+        assert_eq!(document.entry("c").unwrap().comma, None);
+        let (bibliography, errors) = document.lower();
+        assert_eq!(bibliography.get("a").and_then(Entry::title).map(|t| t.plain()), Some("(A)".to_string()));
+        // `c` and `d` lack the required fields
+        assert_eq!(errors.len(), 2);
+
+        let mut document = Document::parse(b"@misc{key}");
+        document.entry_mut("key").unwrap().set_field("title", "T");
+        assert_eq!(document.to_string(), "@misc{key,\n  title = {T}\n}");
+    }
+
+    #[test]
+    fn percent_signs() {
+        let raw = b"Some 50% text @misc{a, editor = {A}, title = {A}, date = 2001}\n  % @misc{b}\n@misc{c, editor = {C}, title = {C}, date = 2003}";
+        let document = Document::parse(raw);
+        assert_eq!(document.to_string().as_bytes(), &raw[..]);
+        let (bibliography, errors) = document.lower();
+        assert_eq!(bibliography.keys().collect::<Vec<_>>(), vec!["a", "c"]);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let raw = b"@misc{a, editor = {M\xfcller}, title = {Gr\xfc\xdfe}, date = 2001}\n@misc{b\xff, title = {B}}\n";
+        let mut document = Document::parse(raw);
+        assert_eq!(document.to_bytes(), &raw[..]);
+        let title = &document.entry("a").unwrap().field("title").unwrap().value.tokens[0];
+        assert_eq!(title.text, "{Gr\u{fffd}\u{fffd}e}");
+        let (bibliography, _) = document.lower();
+        assert_eq!(bibliography.get("a").and_then(Entry::title).map(|t| t.plain()), Some("Gr\u{fffd}\u{fffd}e".to_string()));
+
+        document.entry_mut("a").unwrap().set_field("year", "2001");
+        let expected = b"@misc{a, editor = {M\xfcller}, title = {Gr\xfc\xdfe}, date = 2001, year = {2001}}\n";
+        assert!(document.to_bytes().starts_with(expected));
+    }
+}
@@ -0,0 +1,209 @@
+use pom::Parser;
+use pom::parser::*;
+use pom::char_class::digit;
+use parser::sp0;
+use biblatex::{Date, DatePart, RangeEnd, Season, Unspecified};
+
+// Dates are read as Biber reads them: ISO 8601 dates and ranges, along with
+// the EDTF extensions for BCE years, uncertain (`?`) and approximate (`~`)
+// dates, unspecified digits (`X`) and seasons.
+
+// number made up of exactly `n` digits
+fn digits(n: usize) -> Parser<u8, i32> {
+    let digits = is_a(digit).repeat(n..n + 1).convert(String::from_utf8);
+    digits.convert(|s| s.parse::<i32>())
+}
+
+fn is_leap(year: i32) -> bool {
+    year.rem_euclid(4) == 0 && (year.rem_euclid(100) != 0 || year.rem_euclid(400) == 0)
+}
+
+fn days_in_month(year: i32, month: i32) -> i32 {
+    match month {
+        2 if is_leap(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn day(year: i32, month: i32, day: i32) -> DatePart {
+    DatePart {
+        month: Some(month as u8),
+        day: Some(day as u8),
+        .. DatePart::year(year)
+    }
+}
+
+// number made up of at least `n` digits
+fn number(n: usize) -> Parser<u8, i32> {
+    let digits = is_a(digit).repeat(n..).convert(String::from_utf8);
+    digits.convert(|s| s.parse::<i32>())
+}
+
+// number which is negative if preceded by `-`
+fn signed(number: Parser<u8, i32>) -> Parser<u8, i32> {
+    (sym(b'-').opt() + number).map(|(sign, number)| match sign {
+        Some(_) => -number,
+        None => number,
+    })
+}
+
+// year, possibly negative for BCE years; years of more than four digits are
+// prefixed with `Y`, e.g. `Y10000`
+fn year() -> Parser<u8, i32> {
+    (sym(b'Y') * signed(number(5))) | signed(digits(4))
+}
+
+/// Year on its own, as in the legacy `year` field: either as it is written
+/// in a date, or as a number of any length, e.g. `800`
+pub fn plain_year() -> Parser<u8, i32> {
+    (sym(b'Y') * signed(number(5))) | signed(number(1))
+}
+
+// month, or a season in its place
+fn month() -> Parser<u8, (Option<u8>, Option<Season>)> {
+    digits(2).convert(|month| match month {
+        1..=12 => Ok((Some(month as u8), None)),
+        21 => Ok((None, Some(Season::Spring))),
+        22 => Ok((None, Some(Season::Summer))),
+        23 => Ok((None, Some(Season::Autumn))),
+        24 => Ok((None, Some(Season::Winter))),
+        _ => Err("invalid month"),
+    })
+}
+
+// `?` for uncertain, `~` for approximate, `%` for both
+fn qualifier() -> Parser<u8, (bool, bool)> {
+    one_of(b"?~%").opt().map(|q| match q {
+        Some(b'?') => (true, false),
+        Some(b'~') => (false, true),
+        Some(b'%') => (true, true),
+        _ => (false, false),
+    })
+}
+
+fn date_part() -> Parser<u8, DatePart> {
+    let month_day = sym(b'-') * month() + (sym(b'-') * digits(2)).opt();
+    let part = year() + month_day.opt() + qualifier();
+    part.convert(|((year, month_day), (uncertain, approximate))| {
+        let mut part = DatePart { uncertain, approximate, .. DatePart::year(year) };
+        if let Some(((month, season), day)) = month_day {
+            part.month = month;
+            part.season = season;
+            match (month, day) {
+                (Some(m), Some(d)) if d >= 1 && d <= days_in_month(year, m as i32) => part.day = Some(d as u8),
+                (_, Some(_)) => return Err("invalid day"),
+                (_, None) => (),
+            }
+        }
+        Ok(part)
+    })
+}
+
+// date with unspecified digits, which stands for the range of dates it
+// could be
+fn unspecified() -> Parser<u8, Date> {
+    let range = |start, end, unspecified| Date {
+        start,
+        end: Some(RangeEnd::Date(end)),
+        unspecified: Some(unspecified),
+    };
+    let decade = (digits(3) - sym(b'X')).map(move |y| {
+        range(DatePart::year(y * 10), DatePart::year(y * 10 + 9), Unspecified::YearInDecade)
+    });
+    let century = (digits(2) - seq(b"XX")).map(move |y| {
+        range(DatePart::year(y * 100), DatePart::year(y * 100 + 99), Unspecified::YearInCentury)
+    });
+    let day_in_year = (digits(4) - seq(b"-XX-XX")).map(move |y| {
+        range(day(y, 1, 1), day(y, 12, 31), Unspecified::DayInYear)
+    });
+    let month_in_year = (digits(4) - seq(b"-XX")).map(move |y| {
+        let month = |m| DatePart { month: Some(m), .. DatePart::year(y) };
+        range(month(1), month(12), Unspecified::MonthInYear)
+    });
+    let day_in_month = (digits(4) - sym(b'-') + digits(2) - seq(b"-XX")).convert(move |(y, m)| match m {
+        1..=12 => Ok(range(day(y, m, 1), day(y, m, days_in_month(y, m)), Unspecified::DayInMonth)),
+        _ => Err("invalid month"),
+    });
+    decade | century | day_in_year | day_in_month | month_in_year
+}
+
+/// Date or range of dates, e.g. `2004-02-29`, `2004/2006`, `2004/` or `199X`
+pub fn date() -> Parser<u8, Date> {
+    let end = seq(b"..").map(|_| RangeEnd::Open) | date_part().map(RangeEnd::Date) | empty().map(|_| RangeEnd::Open);
+    let range = (date_part() + (sym(b'/') * end).opt()).map(|(start, end)| Date {
+        start,
+        end,
+        unspecified: None,
+    });
+    sp0() * (unspecified() | range) - sp0()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pom::DataInput;
+
+    fn parse_date(raw: &str) -> Option<Date> {
+        (date() - end()).parse(&mut DataInput::new(raw.as_bytes())).ok()
+    }
+
+    #[test]
+    fn simple() {
+        let expected = Date::year(2004);
+        assert_eq!(parse_date("2004"), Some(expected));
+
+        let expected = Date::year(-44);
+        assert_eq!(parse_date("-0044"), Some(expected));
+
+        let expected = Date { start: day(2004, 2, 29), .. Date::default() };
+        assert_eq!(parse_date(" 2004-02-29 "), Some(expected));
+
+        assert_eq!(parse_date("2003-02-29"), None);
+        assert_eq!(parse_date("2004-13"), None);
+        assert_eq!(parse_date("04"), None);
+
+        let expected = Date::year(12345);
+        assert_eq!(parse_date("Y12345"), Some(expected));
+        assert_eq!(parse_date("12345"), None);
+    }
+
+    #[test]
+    fn ranges() {
+        let expected = Date {
+            start: DatePart { month: Some(2), .. DatePart::year(2004) },
+            end: Some(RangeEnd::Date(DatePart::year(2006))),
+            unspecified: None,
+        };
+        assert_eq!(parse_date("2004-02/2006"), Some(expected));
+
+        let expected = Date { end: Some(RangeEnd::Open), .. Date::year(2004) };
+        assert_eq!(parse_date("2004/"), Some(expected.clone()));
+        assert_eq!(parse_date("2004/.."), Some(expected));
+    }
+
+    #[test]
+    fn edtf() {
+        let expected = DatePart { uncertain: true, approximate: true, .. DatePart::year(1850) };
+        assert_eq!(parse_date("1850%").map(|d| d.start), Some(expected));
+
+        let expected = DatePart { season: Some(Season::Summer), approximate: true, .. DatePart::year(2004) };
+        assert_eq!(parse_date("2004-22~").map(|d| d.start), Some(expected));
+
+        let expected = Date {
+            start: DatePart::year(1990),
+            end: Some(RangeEnd::Date(DatePart::year(1999))),
+            unspecified: Some(Unspecified::YearInDecade),
+        };
+        assert_eq!(parse_date("199X"), Some(expected));
+
+        let date = parse_date("2004-02-XX").unwrap();
+        assert_eq!(date.end, Some(RangeEnd::Date(day(2004, 2, 29))));
+        assert_eq!(date.unspecified, Some(Unspecified::DayInMonth));
+
+        for raw in &["2004-02-29", "-0044", "0800", "Y-12000", "2004-02/2006", "2004/", "1850%", "2004-22~", "19XX", "1999-XX-XX", "2004-02-XX"] {
+            assert_eq!(parse_date(raw).unwrap().to_string(), *raw);
+        }
+    }
+}
@@ -3,8 +3,10 @@ use std::fmt;
 use pom::Parser;
 use pom::parser::*;
 use pom::char_class::alphanum;
-use parser::{sp0, msp0, field};
-use biblatex::{Entry, Field};
+use parser::{msp0, field, canonical_name, legacy_year, month, raw_field, RawField, EntryNode, Options};
+use parser::macros::Macros;
+use biblatex::{Date, Entry, Field};
+use error::ErrorKind;
 
 // recognises chars that can make up a citation key: a-zA-Z0-9_/:-
 fn cite_key_char(c: u8) -> bool {
@@ -22,12 +24,12 @@ impl fmt::Display for MatchFail {
 
 impl Error for MatchFail {
     fn description(&self) -> &str { "String match failed." }
-    fn cause(&self) -> Option<&Error> { None }
+    fn cause(&self) -> Option<&dyn Error> { None }
 }
 
 // attempts to match some bytes in a case-insensitive way,
 // errors out if it fails (so that the parser can backtrack)
-fn insensitive_match(bytes: Vec<u8>, rhs: &'static str) -> Result<(), Box<Error>> {
+fn insensitive_match(bytes: Vec<u8>, rhs: &'static str) -> Result<(), Box<dyn Error>> {
     let lowercase = String::from_utf8(bytes)?.to_lowercase();
     if lowercase == rhs {
         Ok(())
@@ -37,33 +39,269 @@ fn insensitive_match(bytes: Vec<u8>, rhs: &'static str) -> Result<(), Box<Error>
 }
 
 // bibliographic entry tag, e.g. `@article`
-fn tag(name: &'static str) -> Parser<u8, ()> {
+pub fn tag(name: &'static str) -> Parser<u8, ()> {
     let matcher = move |bs| insensitive_match(bs, name);
     sym(b'@') * is_a(alphanum).repeat(1..).convert(matcher).discard()
 }
 
-fn cite_key() -> Parser<u8, String> {
-    is_a(cite_key_char).repeat(1..).convert(|bs| String::from_utf8(bs))
+// tag of an entry of any type, returned in lowercase
+fn entry_type() -> Parser<u8, String> {
+    let name = is_a(alphanum).repeat(1..).convert(String::from_utf8);
+    sym(b'@') * name.map(|s| s.to_lowercase())
 }
 
-fn entry_template(name: &'static str) -> Parser<u8, (String, Vec<Field>)> {
-    let tag_line = tag(name) * msp0() * sym(b'{') * msp0();
-    let open = tag_line * cite_key() - sp0() - sym(b',') - msp0();
-    let close = sym(b',').opt() * msp0() * sym(b'}');
-    let fields = list(field(), sp0() * sym(b',') - msp0() -!sym(b'}'));
-    open + fields - close
+pub fn cite_key() -> Parser<u8, String> {
+    is_a(cite_key_char).repeat(1..).convert(String::from_utf8)
 }
 
-pub fn entry() -> Parser<u8, (String, Option<Entry>)> {
-    entry_template("article").map(|(k, fs)| (k, Entry::new_article(fs))) |
-    entry_template("inproceedings").map(|(k, fs)| (k, Entry::new_in_proceedings(fs)))
+/// Opening of an entry up to its cite key, e.g. `@article{key`
+pub fn entry_key() -> Parser<u8, String> {
+    entry_type() * msp0() * one_of(b"{(") * msp0() * cite_key()
+}
+
+/// Entry as written in the input, before macro expansion
+#[derive(Debug, PartialEq)]
+pub struct RawEntry {
+    pub entry_type: String,
+    pub key: String,
+    pub fields: Vec<RawField>,
+    /// Position of the entry in the input
+    pub position: usize,
+}
+
+/// Lowers an entry from the syntax tree
+pub fn raw_entry(node: &EntryNode) -> RawEntry {
+    RawEntry {
+        entry_type: node.entry_type(),
+        key: node.key().to_string(),
+        fields: node.fields.iter().map(raw_field).collect(),
+        position: node.tag.span.start,
+    }
+}
+
+// the legacy BibTeX thesis and report types carry their kind in the tag,
+// which becomes the `type` field unless one is given explicitly
+fn with_default_type(mut fields: Vec<Field>, kind: &str) -> Vec<Field> {
+    let has_type = fields.iter().any(|f| matches!(*f, Field::Type(_)));
+    if !has_type {
+        fields.push(Field::Type(kind.to_string()));
+    }
+    fields
+}
+
+// types which BibLaTeX recognises but its standard styles treat as `@misc`
+const MISC_TYPES: [&str; 15] = [
+    "artwork", "audio", "bibnote", "commentary", "image", "jurisdiction",
+    "legislation", "legal", "letter", "movie", "music", "performance", "review",
+    "standard", "video",
+];
+
+// an entry of one of those types becomes a `@misc`, which keeps the type in
+// its `entrysubtype` field unless one is given explicitly
+fn with_entry_subtype(mut fields: Vec<Field>, kind: &str) -> Vec<Field> {
+    let has_subtype = fields.iter().any(|f| f.name() == "entrysubtype");
+    if !has_subtype {
+        fields.push(Field::Unknown("entrysubtype".to_string(), kind.to_string()));
+    }
+    fields
+}
+
+// takes the legacy `year` and `month` fields out of the expanded fields and
+// combines them into a date, returned along with the position of the year
+fn legacy_date(fields: &mut Vec<(String, String, usize)>) -> Option<Result<(Date, usize), (usize, ErrorKind)>> {
+    let take = |fields: &mut Vec<(String, String, usize)>, name| {
+        fields.iter().position(|f| f.0.eq_ignore_ascii_case(name)).map(|i| fields.remove(i))
+    };
+    let (_, year, position) = take(fields, "year")?;
+    let month = take(fields, "month");
+    Some(legacy_year(&year).map_err(|e| (position, e)).and_then(|mut date| {
+        if let Some((_, month, position)) = month {
+            date.start.month = Some(self::month(&month).map_err(|e| (position, e))?);
+        }
+        Ok((date, position))
+    }))
+}
+
+/// Entry whose macros are expanded and whose fields are read, which can still
+/// inherit fields from other entries before it is instantiated
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreparedEntry {
+    /// BibLaTeX type of the entry, which legacy BibTeX types are mapped onto
+    pub entry_type: String,
+    pub key: String,
+    pub fields: Vec<Field>,
+    /// Fields which were given under another name, with that name
+    pub aliases: Vec<(String, String)>,
+    /// Position of the entry in the input
+    pub position: usize,
+}
+
+/// Expands the macros in an entry and reads its fields
+///
+/// In case of failure, the error is returned along with its position, while
+/// warnings about the entry are added to `warnings` either way.
+pub fn prepare_entry(
+    raw: RawEntry,
+    macros: &Macros,
+    options: &Options,
+    warnings: &mut Vec<(usize, ErrorKind)>,
+) -> Result<PreparedEntry, (usize, ErrorKind)> {
+    let mut expanded = Vec::new();
+    for f in raw.fields {
+        let value = macros.expand(&f.value)?;
+        expanded.push((f.name, value, f.position));
+    }
+    // aliases and names in another case are recorded, so that the fields
+    // can be written back out under the same names; this includes `year` and
+    // `month`, which are about to become part of the date
+    let mut aliases = Vec::new();
+    for (name, _, _) in &expanded {
+        let canonical = canonical_name(name);
+        if canonical != *name {
+            aliases.push((canonical, name.clone()));
+        }
+    }
+    let legacy = legacy_date(&mut expanded);
+    let mut fields = Vec::new();
+    for (name, value, position) in expanded {
+        fields.push(field(&name, &value, options).map_err(|e| (position, e))?);
+    }
+
+    // `year` and `month` make up the date of the entry, unless there is a
+    // `date` field, which takes precedence as it does in BibLaTeX
+    let date = fields.iter().find_map(|f| match *f {
+        Field::Date(ref date) => Some(date),
+        _ => None,
+    });
+    match (date, legacy) {
+        (Some(date), Some(Ok((legacy, position)))) => {
+            let start = &legacy.start;
+            if start.year != date.start.year || start.month.is_some_and(|m| date.start.month != Some(m)) {
+                let kind = ErrorKind::ConflictingDate {
+                    date: Box::new(date.clone()),
+                    legacy: Box::new(legacy),
+                };
+                warnings.push((position, kind));
+            }
+        }
+        // legacy fields which cannot be read are only an error if they are
+        // the only date there is
+        (Some(_), _) => (),
+        (None, Some(legacy)) => fields.push(Field::Date(legacy?.0)),
+        (None, None) => (),
+    }
+
+    // BibTeX entry types which BibLaTeX only supports as aliases
+    let (entry_type, fields) = match raw.entry_type.as_str() {
+        "conference" => ("inproceedings", fields),
+        "electronic" | "www" => ("online", fields),
+        "phdthesis" => ("thesis", with_default_type(fields, "phdthesis")),
+        "mastersthesis" => ("thesis", with_default_type(fields, "mathesis")),
+        "techreport" => ("report", with_default_type(fields, "techreport")),
+        other if MISC_TYPES.contains(&other) => ("misc", with_entry_subtype(fields, other)),
+        other => (other, fields),
+    };
+    Ok(PreparedEntry {
+        entry_type: entry_type.to_string(),
+        key: raw.key,
+        fields,
+        aliases,
+        position: raw.position,
+    })
+}
+
+/// Instantiates a prepared entry, checking that it is valid for its type
+///
+/// Warnings about fields which the type does not model, and which are kept
+/// as extra fields, are added to `warnings`.
+pub fn instantiate(
+    prepared: PreparedEntry,
+    warnings: &mut Vec<(usize, ErrorKind)>,
+) -> Result<(String, Entry), (usize, ErrorKind)> {
+    let fields = prepared.fields;
+    let entry = match prepared.entry_type.as_str() {
+        "article" => Entry::new_article(fields),
+        "book" => Entry::new_book(fields),
+        "mvbook" => Entry::new_mv_book(fields),
+        "inbook" => Entry::new_in_book(fields),
+        "bookinbook" => Entry::new_book_in_book(fields),
+        "suppbook" => Entry::new_supp_book(fields),
+        "booklet" => Entry::new_booklet(fields),
+        "collection" => Entry::new_collection(fields),
+        "mvcollection" => Entry::new_mv_collection(fields),
+        "incollection" => Entry::new_in_collection(fields),
+        "suppcollection" => Entry::new_supp_collection(fields),
+        "dataset" => Entry::new_dataset(fields),
+        "manual" => Entry::new_manual(fields),
+        "misc" => Entry::new_misc(fields),
+        "online" => Entry::new_online(fields),
+        "patent" => Entry::new_patent(fields),
+        "periodical" => Entry::new_periodical(fields),
+        "suppperiodical" => Entry::new_supp_periodical(fields),
+        "proceedings" => Entry::new_proceedings(fields),
+        "mvproceedings" => Entry::new_mv_proceedings(fields),
+        "inproceedings" => Entry::new_in_proceedings(fields),
+        "reference" => Entry::new_reference(fields),
+        "mvreference" => Entry::new_mv_reference(fields),
+        "inreference" => Entry::new_in_reference(fields),
+        "report" => Entry::new_report(fields),
+        "set" => Entry::new_set(fields),
+        "software" => Entry::new_software(fields),
+        "thesis" => Entry::new_thesis(fields),
+        "unpublished" => Entry::new_unpublished(fields),
+        "xdata" => Entry::new_xdata(fields),
+        "customa" => Entry::new_custom_a(fields),
+        "customb" => Entry::new_custom_b(fields),
+        "customc" => Entry::new_custom_c(fields),
+        "customd" => Entry::new_custom_d(fields),
+        "custome" => Entry::new_custom_e(fields),
+        "customf" => Entry::new_custom_f(fields),
+        _ => {
+            let kind = ErrorKind::UnknownEntryType(prepared.entry_type.clone());
+            return Err((prepared.position, kind));
+        }
+    };
+    match entry {
+        Ok((mut entry, mut unexpected)) => {
+            for (name, original) in prepared.aliases {
+                let lowercase = original.to_lowercase();
+                let alias = lowercase != name;
+                if alias && unexpected.contains(&name) {
+                    // an alias for a field which the type does not model,
+                    // e.g. `address` for an `@article`, is kept as it was
+                    // given, since that is likely how it is meant
+                    let value = entry.extra_mut().remove(&name).unwrap_or_default();
+                    entry.extra_mut().insert(&lowercase, &value);
+                    unexpected.retain(|n| *n != name);
+                    if lowercase != original {
+                        entry.original_names_mut().insert(&lowercase, &original);
+                    }
+                    continue;
+                }
+                entry.original_names_mut().insert(&name, &original);
+            }
+            for name in unexpected {
+                warnings.push((prepared.position, ErrorKind::UnexpectedField(name)));
+            }
+            Ok((prepared.key, entry))
+        }
+        Err(e) => Err((prepared.position, ErrorKind::InvalidEntry(Box::new(e)))),
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use pom::DataInput;
-    use biblatex::{Article, Name, NameList};
+    use parser::{cst, parse_bib};
+    use writer::{Dialect, Writer};
+    use biblatex::{Article, Book, Thesis, Misc, Name, NameList, InvalidEntry, Text, Chunk};
+
+    fn parse_entry(raw: &[u8]) -> Result<(String, Entry), (usize, ErrorKind)> {
+        let node = cst::entry().parse(&mut DataInput::new(raw)).unwrap();
+        let prepared = prepare_entry(raw_entry(&node), &Macros::predefined(), &Options::default(), &mut Vec::new())?;
+        instantiate(prepared, &mut Vec::new())
+    }
 
     #[test]
     fn match_tag() {
@@ -80,14 +318,15 @@ mod test {
   date         = 2004,
   volume       = 12,
 }"#;
-        let mut data = DataInput::new(raw);
         let name1 = Name {
             family: "Baez".to_string(),
             given: Some("John C.".to_string()),
+            .. Name::default()
         };
         let name2 = Name {
             family: "Lauda".to_string(),
             given: Some("Aaron D.".to_string()),
+            .. Name::default()
         };
         let author = NameList {
             names: vec![name1, name2],
@@ -95,17 +334,344 @@ mod test {
         };
         let expected = (
             "baez/article".to_string(),
-            Some(Entry::Article(
+            Entry::Article(
                 Article {
-                    author: author,
-                    title: "Higher-Dimensional Algebra V: 2-Groups".to_string(),
-                    journal_title: "Theory and Applications of Categories".to_string(),
-                    year: 2004,
+                    author,
+                    title: Text::new(vec![
+                        Chunk::Normal("Higher-Dimensional Algebra ".to_string()),
+                        Chunk::Protected("V".to_string()),
+                        Chunk::Normal(": 2-Groups".to_string()),
+                    ]),
+                    journal_title: Text::from("Theory and Applications of Categories"),
+                    date: Date::year(2004),
                     volume: Some("12".to_string()),
                     .. Article::default()
                 }
-            ))
+            )
         );
-        assert_eq!(entry().parse(&mut data), Ok(expected));
+        assert_eq!(parse_entry(raw), Ok(expected));
+    }
+
+    #[test]
+    fn book() {
+        let raw = br#"@Book{maclane,
+  author    = {Mac Lane, Saunders},
+  title     = {Categories for the Working Mathematician},
+  edition   = 2,
+  publisher = {Springer},
+  date      = 1998,
+}"#;
+        let author = NameList {
+            names: vec![Name {
+                family: "Mac Lane".to_string(),
+                given: Some("Saunders".to_string()),
+                .. Name::default()
+            }],
+            truncated: false,
+        };
+        let expected = (
+            "maclane".to_string(),
+            Entry::Book(
+                Book {
+                    author,
+                    title: Text::from("Categories for the Working Mathematician"),
+                    date: Date::year(1998),
+                    edition: Some("2".to_string()),
+                    publisher: Some("Springer".to_string()),
+                    .. Book::default()
+                }
+            )
+        );
+        assert_eq!(parse_entry(raw), Ok(expected));
+    }
+
+    #[test]
+    fn legacy_thesis() {
+        let raw = br#"@phdthesis{doe,
+  author      = {Doe, Jane},
+  title       = {On Things},
+  institution = {University of Nowhere},
+  year        = 2010,
+}"#;
+        let author = NameList {
+            names: vec![Name {
+                family: "Doe".to_string(),
+                given: Some("Jane".to_string()),
+                .. Name::default()
+            }],
+            truncated: false,
+        };
+        let expected = (
+            "doe".to_string(),
+            Entry::Thesis(
+                Thesis {
+                    author,
+                    title: Text::from("On Things"),
+                    kind: "phdthesis".to_string(),
+                    institution: "University of Nowhere".to_string(),
+                    date: Date::year(2010),
+                    .. Thesis::default()
+                }
+            )
+        );
+        assert_eq!(parse_entry(raw), Ok(expected));
+    }
+
+    #[test]
+    fn aliases() {
+        let raw = br#"@phdthesis{doe,
+  author  = {Doe, Jane},
+  title   = {On Things},
+  school  = {University of Nowhere},
+  address = {Nowhere},
+  year    = 2010,
+}"#;
+        let (_, thesis) = parse_entry(raw).unwrap();
+        match thesis {
+            Entry::Thesis(Thesis { ref institution, ref location, .. }) => {
+                assert_eq!(institution, "University of Nowhere");
+                assert_eq!(location.as_deref(), Some("Nowhere"));
+            }
+            ref other => panic!("unexpected entry {:?}", other),
+        }
+        let names: Vec<(&str, &str)> = thesis.original_names().iter().collect();
+        assert_eq!(names, vec![("institution", "school"), ("location", "address")]);
+
+        let raw = b"@article{a, author = {Doe, Jane}, title = {T}, journal = {J}, journaltitle = {J}, year = 2010}";
+        let expected = InvalidEntry {
+            missing: vec![],
+            duplicated: vec!["journaltitle".to_string()],
+            invalid: vec![],
+        };
+        assert_eq!(parse_entry(raw), Err((0, ErrorKind::InvalidEntry(Box::new(expected)))));
+    }
+
+    #[test]
+    fn field_case() {
+        let raw = b"@ARTICLE{a, AUTHOR = {Doe, Jane}, Title = {T}, Journal = {J}, YEAR = 2010, Month = 3, ISBN = {1}}";
+        let (_, article) = parse_entry(raw).unwrap();
+        assert_eq!(article.date().map(|d| d.to_string()), Some("2010-03".to_string()));
+        assert_eq!(article.extra().get("isbn"), Some("1"));
+        let names: Vec<(&str, &str)> = article.original_names().iter().collect();
+        assert_eq!(names, vec![
+            ("author", "AUTHOR"),
+            ("title", "Title"),
+            ("journaltitle", "Journal"),
+            ("year", "YEAR"),
+            ("month", "Month"),
+            ("isbn", "ISBN"),
+        ]);
+
+        let writer = Writer { dialect: Dialect::BibTeX, align_equals: false, .. Writer::default() };
+        let expected = "@article{a,\n  AUTHOR = {Doe, Jane},\n  Title = {T},\n  Journal = {J},\n  YEAR = {2010},\n  Month = mar,\n  ISBN = {1},\n}";
+        assert_eq!(writer.entry("a", &article), expected);
+    }
+
+    #[test]
+    fn author_or_editor() {
+        let raw = b"@misc{notes, editor = {Doe, Jane}, title = {Notes}, year = 2010}";
+        let (key, misc) = parse_entry(raw).unwrap();
+        assert_eq!(key, "notes");
+        match misc {
+            Entry::Misc(Misc { author: None, editor: Some(_), .. }) => (),
+            other => panic!("unexpected entry {:?}", other),
+        }
+
+        let raw = b"@misc{notes, title = {Notes}}";
+        let invalid = InvalidEntry {
+            missing: vec!["date".to_string(), "author/editor".to_string()],
+            duplicated: vec![],
+            invalid: vec![],
+        };
+        assert_eq!(parse_entry(raw), Err((0, ErrorKind::InvalidEntry(Box::new(invalid)))));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn extra_fields() {
+        let raw = br#"@book{maclane,
+  author    = {Mac Lane, Saunders},
+  title     = {Categories for the Working Mathematician},
+  ISBN      = {0-387-98403-8},
+  date      = 1998,
+  keywords  = {category {theory}},
+  note      = "Second edition",
+}"#;
+        let (_, mut book) = parse_entry(raw).unwrap();
+        let extra: Vec<(&str, &str)> = book.extra().iter().collect();
+        assert_eq!(extra, vec![
+            ("isbn", "0-387-98403-8"),
+            ("keywords", "category {theory}"),
+            ("note", "Second edition"),
+        ]);
+        assert_eq!(book.extra().get("Note"), Some("Second edition"));
+
+        assert_eq!(book.extra_mut().insert("isbn", "978-0-387-98403-2"), Some("0-387-98403-8".to_string()));
+        assert_eq!(book.extra_mut().remove("keywords"), Some("category {theory}".to_string()));
+        book.extra_mut().insert("abstract", "Classic");
+        let names: Vec<&str> = book.extra().iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["isbn", "note", "abstract"]);
+
+        let raw = b"@misc{m, author = {Doe, Jane}, title = {T}, year = 2010, note = {A}, Note = {B}}";
+        let invalid = InvalidEntry {
+            missing: vec![],
+            duplicated: vec!["note".to_string()],
+            invalid: vec![],
+        };
+        assert_eq!(parse_entry(raw), Err((0, ErrorKind::InvalidEntry(Box::new(invalid)))));
+    }
+
+    #[test]
+    fn invalid_fields() {
+        let raw = br#"@book{maclane,
+  author    = {Mac Lane, Saunders},
+  title     = {Categories for the Working Mathematician},
+  title     = {Categories},
+  issue     = 2,
+  date      = 1998,
+  year      = 1997,
+}"#;
+        let invalid = InvalidEntry {
+            missing: vec![],
+            duplicated: vec!["title".to_string()],
+            invalid: vec!["issue".to_string()],
+        };
+        assert_eq!(invalid.to_string(),
+                   "duplicated field `title`; field `issue` not valid for this entry type");
+        assert_eq!(parse_entry(raw), Err((0, ErrorKind::InvalidEntry(Box::new(invalid)))));
+
+        let raw = b"@book{b, title = {T}, issue = 2, series = {S}, series = {R}}";
+        let invalid = InvalidEntry {
+            missing: vec!["author".to_string(), "date".to_string()],
+            duplicated: vec!["series".to_string()],
+            invalid: vec!["issue".to_string()],
+        };
+        assert_eq!(parse_entry(raw), Err((0, ErrorKind::InvalidEntry(Box::new(invalid)))));
+
+        // on their own, such fields are kept, but still reported
+        let fields = vec![
+            Field::Author(NameList::default()),
+            Field::Title(Text::from("T")),
+            Field::Date(Date::year(2001)),
+            Field::Issue("2".to_string()),
+        ];
+        let (book, unexpected) = Entry::new_book(fields).unwrap();
+        assert_eq!(unexpected, vec!["issue".to_string()]);
+        assert_eq!(book.extra().get("issue"), Some("2"));
+    }
+
+    #[test]
+    fn misc_types() {
+        let raw = b"@Letter{l, author = {Doe, Jane}, title = {Dear John}, date = 1900}";
+        let (_, letter) = parse_entry(raw).unwrap();
+        assert_eq!(letter.entry_type(), "misc");
+        assert_eq!(letter.extra().get("entrysubtype"), Some("letter"));
+
+        let raw = b"@standard{s, editor = {ISO}, title = {Dates}, date = 2019, entrysubtype = {iso}}";
+        let (_, standard) = parse_entry(raw).unwrap();
+        assert_eq!(standard.extra().get("entrysubtype"), Some("iso"));
+    }
+
+    #[test]
+    fn acm_article() {
+        let raw = br#"@article{acm,
+  author    = {Doe, Jane},
+  title     = {On Things},
+  journal   = {Communications of the ACM},
+  publisher = {ACM},
+  Address   = {New York, NY, USA},
+  year      = 2000,
+}"#;
+        let node = cst::entry().parse(&mut DataInput::new(raw)).unwrap();
+        let prepared = prepare_entry(raw_entry(&node), &Macros::predefined(), &Options::default(), &mut Vec::new()).unwrap();
+        let mut warnings = Vec::new();
+        let (_, article) = instantiate(prepared, &mut warnings).unwrap();
+        assert_eq!(article.extra().get("address"), Some("New York, NY, USA"));
+        assert_eq!(article.extra().get("location"), None);
+        assert_eq!(article.extra().get("publisher"), Some("ACM"));
+        let names: Vec<(&str, &str)> = article.original_names().iter().collect();
+        assert_eq!(names, vec![("journaltitle", "journal"), ("address", "Address")]);
+        assert_eq!(warnings, vec![(0, ErrorKind::UnexpectedField("publisher".to_string()))]);
+        assert_eq!(parse_bib(raw).map(|b| b.len()), Ok(1));
+    }
+
+    #[test]
+    fn unexpected_fields() {
+        let raw = b"@article{a, author = {Doe, Jane}, title = {T}, journaltitle = {J}, date = 2010, publisher = {ACM}, issue = 2, pages = {1--5}}";
+        let node = cst::entry().parse(&mut DataInput::new(raw)).unwrap();
+        let prepared = prepare_entry(raw_entry(&node), &Macros::predefined(), &Options::default(), &mut Vec::new()).unwrap();
+        let mut warnings = Vec::new();
+        let (_, article) = instantiate(prepared, &mut warnings).unwrap();
+        assert_eq!(article.extra().get("publisher"), Some("ACM"));
+        assert_eq!(article.issue(), Some("2"));
+        assert_eq!(warnings, vec![(0, ErrorKind::UnexpectedField("publisher".to_string()))]);
+        assert!(warnings[0].1.is_warning());
+
+        let raw = b"@misc{m, author = {Doe, Jane}, title = {T}, date = 2010, publisher = {arXiv}, publisher = {arXiv}}";
+        let invalid = InvalidEntry {
+            missing: vec![],
+            duplicated: vec!["publisher".to_string()],
+            invalid: vec!["publisher".to_string()],
+        };
+        assert_eq!(parse_entry(raw), Err((0, ErrorKind::InvalidEntry(Box::new(invalid)))));
+    }
+
+    #[test]
+    fn field_access() {
+        let raw = br#"@misc{notes,
+  editor = {Doe, Jane},
+  title  = {Notes},
+  date   = 2010,
+  url    = {https://example.org},
+  Note   = {Draft},
+}"#;
+        let (_, mut misc) = parse_entry(raw).unwrap();
+        assert_eq!(misc.url(), Some("https://example.org"));
+        assert_eq!(misc.pages(), None);
+        assert_eq!(misc.volume(), None);
+        assert_eq!(misc.get("URL"), Some(Field::Url("https://example.org".to_string())));
+        assert_eq!(misc.get("note"), Some(Field::Unknown("note".to_string(), "Draft".to_string())));
+        assert_eq!(misc.get("volume"), None);
+
+        assert_eq!(misc.set(Field::Url("https://example.com".to_string())),
+                   Ok(Some(Field::Url("https://example.org".to_string()))));
+        assert_eq!(misc.set(Field::Unknown("isbn".to_string(), "1".to_string())), Ok(None));
+        assert_eq!(misc.extra().get("isbn"), Some("1"));
+        // fields which the type does not model are kept as extra fields
+        assert_eq!(misc.set(Field::JournalTitle(Text::from("J"))), Ok(None));
+        assert_eq!(misc.set(Field::Unknown("journal".to_string(), "K".to_string())),
+                   Ok(Some(Field::Unknown("journaltitle".to_string(), "J".to_string()))));
+
+        // fields given as unknown ones under a modelled name or alias are
+        // parsed into the modelled field
+        let old = misc.set(Field::Unknown("Title".to_string(), "New {Notes}".to_string()));
+        assert_eq!(old.map(|f| f.map(|f| f.name().to_string())), Ok(Some("title".to_string())));
+        assert_eq!(misc.title().map(Text::plain), Some("New Notes".to_string()));
+        let invalid = InvalidEntry { missing: vec![], duplicated: vec![], invalid: vec!["date".to_string()] };
+        assert_eq!(misc.set(Field::Unknown("date".to_string(), "soon".to_string())), Err(invalid));
+        assert_eq!(misc.extra().iter().map(|(name, _)| name).collect::<Vec<_>>(), vec!["note", "isbn", "journaltitle"]);
+
+        let missing = |name: &str| InvalidEntry { missing: vec![name.to_string()], duplicated: vec![], invalid: vec![] };
+        assert_eq!(misc.remove("title"), Err(missing("title")));
+        assert_eq!(misc.remove("editor"), Err(missing("author/editor")));
+        assert!(misc.editor().is_some());
+        assert_eq!(misc.remove("note"), Ok(Some(Field::Unknown("note".to_string(), "Draft".to_string()))));
+        assert_eq!(misc.original_names().get("note"), None);
+        assert_eq!(misc.remove("url").map(|f| f.is_some()), Ok(true));
+        assert_eq!(misc.remove("url"), Ok(None));
+
+        let raw = b"@article{a, author = {Doe, Jane}, title = {T}, journal = {J}, date = 2010, address = {Paris}, publisher = {ACM}}";
+        let (_, mut article) = parse_entry(raw).unwrap();
+        let publisher = article.get("publisher").unwrap();
+        assert_eq!(article.set(publisher.clone()), Ok(Some(publisher.clone())));
+        assert_eq!(article.set(Field::Publisher("IEEE".to_string())), Ok(Some(publisher)));
+        assert_eq!(article.extra().get("publisher"), Some("IEEE"));
+        assert_eq!(article.get("Journal").map(|f| f.name().to_string()), Some("journaltitle".to_string()));
+        assert_eq!(article.get("address"), Some(Field::Unknown("address".to_string(), "Paris".to_string())));
+        assert_eq!(article.get("location"), None);
+        assert_eq!(article.set(Field::Unknown("Address".to_string(), "Rome".to_string())),
+                   Ok(Some(Field::Unknown("address".to_string(), "Paris".to_string()))));
+        assert_eq!(article.remove("journal"), Err(missing("journaltitle")));
+        assert_eq!(article.remove("Address"), Ok(Some(Field::Unknown("address".to_string(), "Rome".to_string()))));
+        assert_eq!(article.extra().iter().map(|(name, _)| name).collect::<Vec<_>>(), vec!["publisher"]);
+    }
+}
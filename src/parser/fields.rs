@@ -1,182 +1,317 @@
-use std::str::FromStr;
-use pom::Parser;
+use pom::{DataInput, Parser};
 use pom::parser::*;
-use pom::char_class::digit;
-use parser::{literal, sp0, ranges, name_list};
-use biblatex::Field;
-
-pub fn field() -> Parser<u8, Field> {
-    title_field() |
-    book_title_field() |
-    journal_title_field() |
-    year_or_date_field() |
-    pages_field() |
-    author_field() |
-    editor_field() |
-    url_field() |
-    volume_field() |
-    series_field() |
-    issue_field() |
-    unknown_field()
-}
-
-fn title_field() -> Parser<u8, Field> {
-    let title = call(literal).map(|s| Field::Title(s));
-    seq(b"title") * sp0() * sym(b'=') * sp0() * title
-}
+use parser::dates::plain_year;
+use parser::{sp0, flatten, value, Value, ranges, date, name_list, text, FieldNode, Options};
+use biblatex::{Date, Field};
+use error::ErrorKind;
+use latex;
 
-fn book_title_field() -> Parser<u8, Field> {
-    let title = call(literal).map(|s| Field::BookTitle(s));
-    seq(b"booktitle") * sp0() * sym(b'=') * sp0() * title
+/// Field as written in the input, e.g. `title = {Some Title}`
+#[derive(Debug, PartialEq)]
+pub struct RawField {
+    pub name: String,
+    pub value: Value,
+    /// Position of the field in the input
+    pub position: usize,
 }
 
-fn journal_title_field() -> Parser<u8, Field> {
-    let title = call(literal).map(|s| Field::JournalTitle(s));
-    seq(b"journaltitle") * sp0() * sym(b'=') * sp0() * title
+/// Lowers a field from the syntax tree
+pub fn raw_field(node: &FieldNode) -> RawField {
+    RawField {
+        name: node.name.text.clone(),
+        value: value(&node.value),
+        position: node.name.span.start,
+    }
 }
 
-fn year_or_date_field() -> Parser<u8, Field> {
-    // we extract the year from the `year` field
-    let four_digits = || is_a(digit).repeat(4..5).convert(|bs| String::from_utf8(bs));
-    let year = || four_digits().convert(|s| i32::from_str(&s)).map(|i| Field::Year(i));
-    let year_braced = sym(b'{') * sp0() * year() - sp0() - sym(b'}');
-    let year_field = seq(b"year") * sp0() * sym(b'=') * sp0() * (year() | year_braced);
-
-    // we extract *just* the year from the `date` field
-    let ext_digits = sym(b'{') * sp0() * four_digits() - none_of(b"}\n").repeat(0..) - sym(b'}');
-    let date = ext_digits.convert(|s| i32::from_str(&s)).map(|i| Field::Year(i)) | year();
-    let date_field = seq(b"date") * sp0() * sym(b'=') * sp0() * date;
-
-    year_field | date_field
-}
+// legacy names of fields, along with the BibLaTeX names which Biber maps
+// them onto
+const ALIASES: [(&str, &str); 7] = [
+    ("address", "location"),
+    ("annote", "annotation"),
+    ("archiveprefix", "eprinttype"),
+    ("journal", "journaltitle"),
+    ("key", "sortkey"),
+    ("primaryclass", "eprintclass"),
+    ("school", "institution"),
+];
 
-fn pages_field() -> Parser<u8, Field> {
-    let pages = ranges().map(|rs| Field::Pages(rs));
-    seq(b"pages") * sp0() * sym(b'=') * sp0() * pages
+/// BibLaTeX name of a field given under some name, which is matched
+/// case-insensitively and may be a legacy alias, e.g. `journaltitle` for
+/// `Journal`
+pub fn canonical_name(name: &str) -> String {
+    let name = name.to_lowercase();
+    ALIASES.iter().find(|a| a.0 == name).map_or(name, |a| a.1.to_string())
 }
 
-fn author_field() -> Parser<u8, Field> {
-    let authors = name_list().map(|ns| Field::Author(ns));
-    seq(b"author") * sp0() * sym(b'=') * sp0() * authors
+/// Interprets the (macro-expanded) value of the field called `name`
+///
+/// Legacy aliases are mapped onto the BibLaTeX fields, while fields which are
+/// not modelled are returned as `Field::Unknown`. Values which cannot be
+/// parsed (such as a year which is not a number) are reported as errors.
+pub fn field(name: &str, value: &str, options: &Options) -> Result<Field, ErrorKind> {
+    // LaTeX has to be decoded before the braces are dropped, since they
+    // delimit the arguments of commands such as `\c{c}`, but escaped braces
+    // and `$` signs are only unescaped once the value has been split into
+    // groups, math and names; identifiers are left alone, and so are the
+    // fields which are not modelled
+    let decoded = match options.decode_latex {
+        true => latex::decode_value(value),
+        false => value.to_string(),
+    };
+    let field = match canonical_name(name).as_str() {
+        "title" => parse(text(), &decoded).map(Field::Title),
+        "maintitle" => parse(text(), &decoded).map(Field::MainTitle),
+        "booktitle" => parse(text(), &decoded).map(Field::BookTitle),
+        "journaltitle" => parse(text(), &decoded).map(Field::JournalTitle),
+        "year" => legacy_year(value).ok().map(Field::Date),
+        "date" => parse(date(), value).map(Field::Date),
+        "urldate" => parse(date(), value).map(Field::UrlDate),
+        "eventdate" => parse(date(), value).map(Field::EventDate),
+        "origdate" => parse(date(), value).map(Field::OrigDate),
+        "pages" => parse(ranges(), value).map(Field::Pages),
+        "author" => parse(name_list(), &decoded).map(Field::Author),
+        "editor" => parse(name_list(), &decoded).map(Field::Editor),
+        "url" => Some(Field::Url(flatten(value))),
+        "doi" => Some(Field::Doi(flatten(value))),
+        "eprint" => Some(Field::Eprint(flatten(value))),
+        "volume" => Some(Field::Volume(flatten(&decoded))),
+        "series" => Some(Field::Series(flatten(&decoded))),
+        "number" => Some(Field::Number(flatten(&decoded))),
+        "issue" => Some(Field::Issue(flatten(&decoded))),
+        "edition" => Some(Field::Edition(flatten(&decoded))),
+        "chapter" => Some(Field::Chapter(flatten(&decoded))),
+        "publisher" => Some(Field::Publisher(flatten(&decoded))),
+        "location" => Some(Field::Location(flatten(&decoded))),
+        "institution" => Some(Field::Institution(flatten(&decoded))),
+        "organization" => Some(Field::Organization(flatten(&decoded))),
+        "type" => Some(Field::Type(flatten(&decoded))),
+        "entryset" => Some(Field::EntrySet(flatten(value))),
+        other => Some(Field::Unknown(other.to_string(), value.to_string())),
+    };
+    field.ok_or_else(|| invalid(name, value))
 }
 
-fn editor_field() -> Parser<u8, Field> {
-    let editors = name_list().map(|ns| Field::Editor(ns));
-    seq(b"editor") * sp0() * sym(b'=') * sp0() * editors
+fn invalid(name: &str, value: &str) -> ErrorKind {
+    ErrorKind::InvalidValue {
+        field: name.to_string(),
+        value: value.to_string(),
+    }
 }
 
-fn url_field() -> Parser<u8, Field> {
-    let url = call(literal).map(|s| Field::Url(s));
-    seq(b"url") * sp0() * sym(b'=') * sp0() * url
+/// Interprets the legacy `year` field, which holds just a year
+pub fn legacy_year(value: &str) -> Result<Date, ErrorKind> {
+    parse(year(), value).map(Date::year).ok_or_else(|| invalid("year", value))
 }
 
-fn volume_field() -> Parser<u8, Field> {
-    let volume_literal = call(literal);
-    let volume_numerical = is_a(digit).repeat(1..).convert(|bs| String::from_utf8(bs));
-    let volume = (volume_literal | volume_numerical).map(|s| Field::Volume(s));
-    seq(b"volume") * sp0() * sym(b'=') * sp0() * volume
-}
+const MONTH_NAMES: [&str; 12] = [
+    "january", "february", "march", "april", "may", "june", "july",
+    "august", "september", "october", "november", "december",
+];
 
-fn series_field() -> Parser<u8, Field> {
-    let series_literal = call(literal);
-    let series_numerical = is_a(digit).repeat(1..).convert(|bs| String::from_utf8(bs));
-    let series = (series_literal | series_numerical).map(|s| Field::Series(s));
-    seq(b"series") * sp0() * sym(b'=') * sp0() * series
+/// Interprets the legacy `month` field: a number (which is what the month
+/// macros expand to), or an English month name, possibly abbreviated
+pub fn month(value: &str) -> Result<u8, ErrorKind> {
+    let month = flatten(value).trim().trim_end_matches('.').to_lowercase();
+    let number = match month.parse::<u8>() {
+        Ok(n) => Some(n).filter(|n| (1..=12).contains(n)),
+        Err(_) => MONTH_NAMES.iter()
+            .position(|name| month.len() >= 3 && name.starts_with(&month))
+            .map(|i| i as u8 + 1),
+    };
+    number.ok_or_else(|| invalid("month", value))
 }
 
-fn issue_field() -> Parser<u8, Field> {
-    let issue = call(literal).map(|s| Field::Issue(s));
-    seq(b"issue") * sp0() * sym(b'=') * sp0() * issue
+// runs a parser over a whole field value
+fn parse<T: 'static>(parser: Parser<u8, T>, value: &str) -> Option<T> {
+    let whole = parser - end();
+    whole.parse(&mut DataInput::new(value.as_bytes())).ok()
 }
 
-fn unknown_field() -> Parser<u8, Field> {
-    let name = none_of(b"{}=\n").repeat(1..).discard();
-    let some_string = none_of(b",\n{}").repeat(0..).discard();
-    let unknown = name * sp0() * sym(b'=') * sp0() * (literal().discard() | some_string);
-    unknown.collect().convert(|bs| String::from_utf8(bs)).map(|s| Field::Unknown(s))
+// we extract the year from the `year` field
+fn year() -> Parser<u8, i32> {
+    sp0() * plain_year() - sp0()
 }
 
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use pom::DataInput;
-    use biblatex::{Name, NameList, Range};
+    use parser::cst;
+    use parser::macros::Macros;
+    use biblatex::{Name, NameList, Range, Text, Chunk, DatePart, RangeEnd};
+
+    fn parse_field(raw: &[u8]) -> Field {
+        let node = cst::field().parse(&mut DataInput::new(raw)).unwrap();
+        let raw = raw_field(&node);
+        let value = Macros::predefined().expand(&raw.value).unwrap();
+        field(&raw.name, &value, &Options::default()).unwrap()
+    }
 
     #[test]
     fn titles() {
-        let mut data1 = DataInput::new(b"title={This is a title}");
-        let expected1 = Field::Title("This is a title".to_string());
-        assert_eq!(field().parse(&mut data1), Ok(expected1));
+        let expected1 = Field::Title(Text::from("This is a title"));
+        assert_eq!(parse_field(b"title={This is a title}"), expected1);
+        assert_eq!(parse_field(b"Title={This is a title}"), expected1);
 
-        let mut data2 = DataInput::new(b"booktitle={This is a {Title}}");
-        let expected2 = Field::BookTitle("This is a Title".to_string());
-        assert_eq!(field().parse(&mut data2), Ok(expected2));
+        let expected2 = Field::BookTitle(Text::new(vec![
+            Chunk::Normal("This is a ".to_string()),
+            Chunk::Protected("Title".to_string()),
+        ]));
+        assert_eq!(parse_field(b"booktitle={This is a {Title}}"), expected2);
     }
 
     #[test]
     fn series() {
-        let mut data = DataInput::new(b"series  = 1");
         let expected = Field::Series("1".to_string());
-        assert_eq!(field().parse(&mut data), Ok(expected));
+        assert_eq!(parse_field(b"series  = 1"), expected);
 
-        let mut data = DataInput::new(b"series   = {Moreshet: Studies in {Jewish} History, Literature and Thought}");
         let expected = Field::Series("Moreshet: Studies in Jewish History, Literature and Thought".to_string());
-        assert_eq!(field().parse(&mut data), Ok(expected));
+        assert_eq!(parse_field(b"series   = {Moreshet: Studies in {Jewish} History, Literature and Thought}"), expected);
     }
 
     #[test]
     fn volume() {
-        let mut data = DataInput::new(b"volume    = 1");
         let expected = Field::Volume("1".to_string());
-        assert_eq!(field().parse(&mut data), Ok(expected));
+        assert_eq!(parse_field(b"volume    = 1"), expected);
 
-        let mut data = DataInput::new(b"volume={A}");
         let expected = Field::Volume("A".to_string());
-        assert_eq!(field().parse(&mut data), Ok(expected));
+        assert_eq!(parse_field(b"volume={A}"), expected);
     }
 
     #[test]
     fn year() {
-        let mut data = DataInput::new(b"year=  2017");
-        let expected = Field::Year(2017);
-        assert_eq!(field().parse(&mut data), Ok(expected));
+        let expected = Field::Date(Date::year(2017));
+        assert_eq!(parse_field(b"year=  2017"), expected);
+
+        let expected = Field::Date(Date::year(2017));
+        assert_eq!(parse_field(b"year = { 2017}"), expected);
+
+        let start = DatePart { month: Some(12), day: Some(1), .. DatePart::year(2000) };
+        let expected = Field::Date(Date { start, .. Date::default() });
+        assert_eq!(parse_field(b"date   = {2000-12-01}"), expected);
 
-        let mut data = DataInput::new(b"year = { 2017}");
-        let expected = Field::Year(2017);
-        assert_eq!(field().parse(&mut data), Ok(expected));
+        let expected = Field::UrlDate(Date { end: Some(RangeEnd::Open), .. Date::year(2000) });
+        assert_eq!(parse_field(b"urldate = {2000/}"), expected);
 
-        let mut data = DataInput::new(b"date   = {2000-12-01}");
-        let expected = Field::Year(2000);
-        assert_eq!(field().parse(&mut data), Ok(expected));
+        let expected = Err(ErrorKind::InvalidValue {
+            field: "year".to_string(),
+            value: "n.d.".to_string(),
+        });
+        assert_eq!(field("year", "n.d.", &Options::default()), expected);
+
+        assert_eq!(parse_field(b"year = 800"), Field::Date(Date::year(800)));
+        assert_eq!(parse_field(b"year = {-44}"), Field::Date(Date::year(-44)));
+        assert_eq!(parse_field(b"year = 10000"), Field::Date(Date::year(10000)));
+        assert_eq!(parse_field(b"year = {Y10000}"), Field::Date(Date::year(10000)));
+    }
+
+    #[test]
+    fn months() {
+        assert_eq!(month("3"), Ok(3));
+        assert_eq!(month("{03}"), Ok(3));
+        assert_eq!(month("March"), Ok(3));
+        assert_eq!(month("Sept."), Ok(9));
+        assert_eq!(month("dec"), Ok(12));
+        assert!(month("13").is_err());
+        assert!(month("Ma").is_err());
+        assert!(month("Spring").is_err());
     }
 
     #[test]
     fn pages() {
-        let mut data = DataInput::new(b"pages  =  {100-102}");
         let range = Range {
             start: "100".to_string(),
             end: Some("102".to_string()),
         };
         let expected = Field::Pages(vec![range]);
-        assert_eq!(field().parse(&mut data), Ok(expected));
+        assert_eq!(parse_field(b"pages  =  {100-102}"), expected);
     }
 
     #[test]
     fn author() {
-        let mut data = DataInput::new(b"author       = {Baez, John C. and Lauda, Aaron D.}");
         let name1 = Name {
             family: "Baez".to_string(),
             given: Some("John C.".to_string()),
+            .. Name::default()
+        };
+        let name2 = Name {
+            family: "Lauda".to_string(),
+            given: Some("Aaron D.".to_string()),
+            .. Name::default()
+        };
+        let expected = Field::Author(NameList {
+            names: vec![name1, name2],
+            truncated: false,
+        });
+        assert_eq!(parse_field(b"author       = {Baez, John C. and Lauda, Aaron D.}"), expected);
+    }
+
+    #[test]
+    fn aliases() {
+        let expected = Field::JournalTitle(Text::from("Journal of the ACM"));
+        assert_eq!(parse_field(b"journal = jacm"), expected);
+        assert_eq!(parse_field(b"JOURNAL = jacm"), expected);
+
+        let expected = Field::Unknown("eprintclass".to_string(), "math.CT".to_string());
+        assert_eq!(parse_field(b"primaryclass = {math.CT}"), expected);
+    }
+
+    #[test]
+    fn escapes() {
+        let raw = "Costs \\$5 and \\$10 for \\{M{\\\"u}ller\\}";
+        let decoded = Options { decode_latex: true, .. Options::default() };
+        let expected = Field::Title(Text::from("Costs \\$5 and \\$10 for \\{Müller\\}"));
+        assert_eq!(field("title", raw, &decoded), Ok(expected));
+        match field("title", raw, &Options::default()) {
+            Ok(Field::Title(title)) => assert_eq!(title.plain(), "Costs $5 and $10 for {M\\\"uller}"),
+            other => panic!("unexpected field {:?}", other),
+        }
+        let expected = Field::Publisher("{A} & $B$".to_string());
+        assert_eq!(field("publisher", "\\{A\\} \\& \\$B\\$", &decoded), Ok(expected));
+    }
+
+    #[test]
+    fn unknown() {
+        let expected = Field::Unknown("keywords".to_string(), "category {theory}, algebra".to_string());
+        assert_eq!(parse_field(b"Keywords = {category {theory}, algebra}"), expected);
+        assert_eq!(expected.name(), "keywords");
+
+        let expected = Field::Unknown("note".to_string(), "3 March".to_string());
+        assert_eq!(parse_field(b"note = mar # \" March\""), expected);
+    }
+
+    #[test]
+    fn quoted_and_concatenated() {
+        let expected = Field::Title(Text::new(vec![
+            Chunk::Normal("A ".to_string()),
+            Chunk::Protected("Title".to_string()),
+            Chunk::Normal(" with a Subtitle".to_string()),
+        ]));
+        assert_eq!(parse_field(b"title = \"A {Title}\" # { with a Subtitle}"), expected);
+
+        let range = Range {
+            start: "100".to_string(),
+            end: Some("102".to_string()),
+        };
+        let expected = Field::Pages(vec![range]);
+        assert_eq!(parse_field(b"pages = \"100\" # \"--\" # 102"), expected);
+
+        let name1 = Name {
+            family: "Baez".to_string(),
+            given: Some("John C.".to_string()),
+            .. Name::default()
         };
         let name2 = Name {
             family: "Lauda".to_string(),
             given: Some("Aaron D.".to_string()),
+            .. Name::default()
         };
         let expected = Field::Author(NameList {
             names: vec![name1, name2],
             truncated: false,
         });
-        assert_eq!(field().parse(&mut data), Ok(expected));
+        let raw = b"author = \"Baez, John C.\" # \" and \" # {Lauda, Aaron D.}";
+        assert_eq!(parse_field(raw), expected);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,547 @@
+use std::collections::HashMap;
+use pom::{DataInput, Parser};
+use pom::parser::*;
+use pom::char_class::{alpha, alphanum, multispace};
+use parser::{field, failure_position, Options};
+use parser::entries::PreparedEntry;
+use biblatex::Field;
+use error::{Error, ErrorKind};
+use writer;
+
+// Entries inherit fields from their `crossref` parent following a set of
+// rules, and all the fields of the `@xdata` entries named in their `xdata`
+// field, as they do in Biber. Inherited fields only replace the ones an
+// entry already has where the rules say so.
+
+/// How a field of the parent is inherited by the child
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mapping {
+    pub source: String,
+    /// Field it becomes in the child, or `None` if it is not inherited
+    pub target: Option<String>,
+    /// Whether it replaces the target field if the child has it, unless the
+    /// defaults decide
+    pub override_target: Option<bool>,
+}
+
+/// Rule for the fields which entries of the `targets` types inherit from
+/// entries of the `sources` types, where `*` stands for any type, as
+/// declared with `\DeclareDataInheritance`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub sources: Vec<String>,
+    pub targets: Vec<String>,
+    pub fields: Vec<Mapping>,
+}
+
+/// Defaults for a child of type `target` of a parent of type `source`, which
+/// take the place of the global ones, as given with `\except`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exception {
+    pub source: String,
+    pub target: String,
+    pub inherit_all: Option<bool>,
+    pub override_target: Option<bool>,
+}
+
+/// Rules for inheriting fields through `crossref`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rules {
+    /// Whether fields no rule mentions are inherited under their own name
+    pub inherit_all: bool,
+    /// Whether inherited fields replace those the child already has
+    pub override_target: bool,
+    /// Exceptions to the defaults, where later ones take precedence over
+    /// earlier ones
+    pub exceptions: Vec<Exception>,
+    /// Rules, where later ones take precedence over earlier ones
+    pub rules: Vec<Rule>,
+}
+
+// fields which are never inherited
+const EXCLUDED: [&str; 18] = [
+    "ids", "crossref", "xref", "xdata", "entryset", "entrysubtype", "execute",
+    "label", "options", "presort", "related", "relatedoptions", "relatedstring",
+    "relatedtype", "shorthand", "shorthandintro", "sortinit", "sortkey",
+];
+
+fn strings(names: &[&str]) -> Vec<String> {
+    names.iter().map(|s| s.to_string()).collect()
+}
+
+impl Mapping {
+    /// Field inherited under the given name
+    pub fn inherit(source: &str, target: &str) -> Mapping {
+        Mapping {
+            source: source.to_string(),
+            target: Some(target.to_string()),
+            override_target: None,
+        }
+    }
+
+    /// Field which is not inherited
+    pub fn no_inherit(source: &str) -> Mapping {
+        Mapping { source: source.to_string(), target: None, override_target: None }
+    }
+}
+
+impl Rule {
+    pub fn new(sources: &[&str], targets: &[&str], fields: Vec<Mapping>) -> Rule {
+        Rule {
+            sources: strings(sources),
+            targets: strings(targets),
+            fields,
+        }
+    }
+
+    fn is_generic(&self) -> bool {
+        self.sources.iter().any(|t| t == "*") || self.targets.iter().any(|t| t == "*")
+    }
+
+    fn is_global(&self) -> bool {
+        self.sources.iter().all(|t| t == "*") && self.targets.iter().all(|t| t == "*")
+    }
+}
+
+// rule for a parent whose title becomes e.g. the `booktitle` of the child
+fn title_rule(sources: &[&str], targets: &[&str], prefix: &str) -> Rule {
+    let mut fields = Vec::new();
+    for name in &["title", "subtitle", "titleaddon"] {
+        fields.push(Mapping::inherit(name, &format!("{}{}", prefix, name)));
+    }
+    for name in &["shorttitle", "sorttitle", "indextitle", "indexsorttitle"] {
+        fields.push(Mapping::no_inherit(name));
+    }
+    Rule::new(sources, targets, fields)
+}
+
+/// Biber's default rules
+impl Default for Rules {
+    fn default() -> Rules {
+        let book_parts = ["inbook", "bookinbook", "suppbook"];
+        let collection_parts = ["incollection", "inreference", "suppcollection"];
+        let excluded = EXCLUDED.iter().map(|name| Mapping::no_inherit(name)).collect();
+        let mut rules = vec![Rule::new(&["*"], &["*"], excluded)];
+        rules.push(Rule::new(&["mvbook", "book"], &book_parts, vec![
+            Mapping::inherit("author", "author"),
+            Mapping::inherit("author", "bookauthor"),
+        ]));
+        rules.push(title_rule(&["mvbook"], &["book", "inbook", "bookinbook", "suppbook"], "main"));
+        rules.push(title_rule(
+            &["mvcollection", "mvreference"],
+            &["collection", "reference", "incollection", "inreference", "suppcollection"],
+            "main",
+        ));
+        rules.push(title_rule(&["mvproceedings"], &["proceedings", "inproceedings"], "main"));
+        rules.push(title_rule(&["book"], &book_parts, "book"));
+        rules.push(title_rule(&["collection", "reference"], &collection_parts, "book"));
+        rules.push(title_rule(&["proceedings"], &["inproceedings"], "book"));
+        rules.push(title_rule(&["periodical"], &["article", "suppperiodical"], "journal"));
+        Rules { inherit_all: true, override_target: false, exceptions: vec![], rules }
+    }
+}
+
+fn matches(types: &[String], entry_type: &str) -> bool {
+    types.iter().any(|t| t == "*" || t == entry_type)
+}
+
+impl Rules {
+    // whether all fields are inherited, and whether they replace those of
+    // the child, for the given types of parent and child
+    fn defaults(&self, source: &str, target: &str) -> (bool, bool) {
+        let exceptions: Vec<&Exception> = self.exceptions.iter().rev()
+            .filter(|e| (e.source == "*" || e.source == source) && (e.target == "*" || e.target == target))
+            .collect();
+        let inherit_all = exceptions.iter().find_map(|e| e.inherit_all).unwrap_or(self.inherit_all);
+        let override_target = exceptions.iter().find_map(|e| e.override_target).unwrap_or(self.override_target);
+        (inherit_all, override_target)
+    }
+
+    /// Names under which a child of type `target` inherits the field `name`
+    /// of a parent of type `source`, along with whether each replaces the
+    /// field the child may already have
+    ///
+    /// The last rule which mentions the field decides, rules for specific
+    /// types taking precedence over those with wildcards.
+    pub fn targets(&self, source: &str, target: &str, name: &str) -> Vec<(String, bool)> {
+        let (inherit_all, override_target) = self.defaults(source, target);
+        for generic in &[false, true] {
+            let rule = self.rules.iter().rev()
+                .filter(|r| r.is_generic() == *generic)
+                .filter(|r| matches(&r.sources, source) && matches(&r.targets, target))
+                .find(|r| r.fields.iter().any(|m| m.source == name));
+            if let Some(rule) = rule {
+                return rule.fields.iter().filter(|m| m.source == name).filter_map(|m| {
+                    let target = m.target.clone()?;
+                    Some((target, m.override_target.unwrap_or(override_target)))
+                }).collect();
+            }
+        }
+        match inherit_all {
+            true => vec![(name.to_string(), override_target)],
+            false => vec![],
+        }
+    }
+
+    // whether a field is kept out of the children of any type
+    fn is_excluded(&self, name: &str) -> bool {
+        self.rules.iter().filter(|r| r.is_global())
+            .any(|r| r.fields.iter().any(|m| m.source == name && m.target.is_none()))
+    }
+
+    /// Reads rules declared in the form used by BibLaTeX, on top of Biber's
+    /// default rules, e.g.
+    ///
+    /// ```text
+    /// \DefaultInheritance[\except{book}{inbook}{override=true}]{all=true, override=false}
+    /// \DeclareDataInheritance{mvbook}{inbook}{
+    ///   \inherit{title}{maintitle}
+    ///   \inherit[override=true]{date}{origdate}
+    ///   \noinherit{note}
+    /// }
+    /// ```
+    ///
+    /// `\ResetDataInheritance` removes all but the rules for fields which are
+    /// never inherited, and `%` starts a comment. The `ignore` option of
+    /// `\DeclareDataInheritance`, which only concerns uniqueness, is read but
+    /// has no effect.
+    pub fn parse(config: &str) -> Result<Rules, Error> {
+        let buf = config.as_bytes();
+        let mut input = DataInput::new(buf);
+        let mut rules = Rules::default();
+        let _ = space().parse(&mut input);
+        while input.position < buf.len() {
+            let start = input.position;
+            let name = (sym(b'\\') * is_a(alpha).repeat(1..).convert(String::from_utf8))
+                .parse(&mut DataInput { data: buf, position: start })
+                .unwrap_or_default();
+            let command = match name.as_str() {
+                "DefaultInheritance" => defaults(),
+                "DeclareDataInheritance" => declaration(),
+                "ResetDataInheritance" => (seq(b"\\ResetDataInheritance") - space()).map(|_| Command::Reset),
+                _ => {
+                    let kind = ErrorKind::Syntax(format!("unknown command `\\{}` in inheritance rules", name));
+                    return Err(Error::new(buf, start, None, kind));
+                }
+            };
+            match command.parse(&mut input) {
+                Ok(Command::Default(exceptions, all, override_target)) => {
+                    rules.exceptions.extend(exceptions);
+                    rules.inherit_all = all.unwrap_or(rules.inherit_all);
+                    rules.override_target = override_target.unwrap_or(rules.override_target);
+                }
+                Ok(Command::Declare(rule)) => rules.rules.push(rule),
+                Ok(Command::Reset) => rules.rules.retain(Rule::is_global),
+                Err(e) => return Err(config_error(buf, failure_position(&e, buf))),
+            }
+        }
+        Ok(rules)
+    }
+}
+
+fn config_error(buf: &[u8], position: usize) -> Error {
+    let message = match String::from_utf8_lossy(&buf[position..]).chars().next() {
+        Some(c) => format!("unexpected {:?} in inheritance rules", c),
+        None => "unexpected end of inheritance rules".to_string(),
+    };
+    Error::new(buf, position, None, ErrorKind::Syntax(message))
+}
+
+// declaration in a configuration of the rules
+enum Command {
+    Default(Vec<Exception>, Option<bool>, Option<bool>),
+    Declare(Rule),
+    Reset,
+}
+
+// whitespace and comments
+fn space() -> Parser<u8, ()> {
+    let comment = sym(b'%') * none_of(b"\n").repeat(0..);
+    (is_a(multispace).discard() | comment.discard()).repeat(0..).discard()
+}
+
+fn name() -> Parser<u8, String> {
+    let name = is_a(|c| alphanum(c) || c == b'*').repeat(1..).convert(String::from_utf8);
+    name - space()
+}
+
+// braced argument, e.g. `{book, inbook}`
+fn names() -> Parser<u8, Vec<String>> {
+    sym(b'{') * space() * list(name(), sym(b',') * space()) - sym(b'}') - space()
+}
+
+fn single_name() -> Parser<u8, String> {
+    sym(b'{') * space() * name() - sym(b'}') - space()
+}
+
+// `key=value` pairs, where the value may be omitted for `true`, e.g.
+// `override, all=false`
+fn key_values(keys: &'static [&'static str]) -> Parser<u8, Vec<(String, bool)>> {
+    let value = (seq(b"true").map(|_| true) | seq(b"false").map(|_| false)) - space();
+    let option = (name() + (sym(b'=') * space() * value).opt()).convert(move |(key, value)| {
+        match keys.contains(&key.as_str()) {
+            true => Ok((key, value.unwrap_or(true))),
+            false => Err(format!("unknown option `{}`", key)),
+        }
+    });
+    list(option, sym(b',') * space())
+}
+
+// optional argument, e.g. `[override=true]`
+fn options(keys: &'static [&'static str]) -> Parser<u8, Vec<(String, bool)>> {
+    let options = sym(b'[') * space() * key_values(keys) - sym(b']') - space();
+    options.opt().map(Option::unwrap_or_default)
+}
+
+fn option(options: &[(String, bool)], key: &str) -> Option<bool> {
+    options.iter().rev().find(|o| o.0 == key).map(|o| o.1)
+}
+
+// `\inherit[override]{title}{booktitle}` or `\noinherit{title}`
+fn mapping() -> Parser<u8, Mapping> {
+    let inherit = seq(b"\\inherit") * space() * options(&["override"]) + single_name() + single_name();
+    let inherit = inherit.map(|((options, source), target)| Mapping {
+        override_target: option(&options, "override"),
+        .. Mapping::inherit(&source, &target)
+    });
+    let no_inherit = seq(b"\\noinherit") * space() * single_name().map(|name| Mapping::no_inherit(&name));
+    no_inherit | inherit
+}
+
+// `{all=true, override=false}`
+fn default_options() -> Parser<u8, Vec<(String, bool)>> {
+    sym(b'{') * space() * key_values(&["all", "override"]) - sym(b'}') - space()
+}
+
+// `\except{book}{inbook}{override=true}`
+fn exception() -> Parser<u8, Exception> {
+    let exception = seq(b"\\except") * space() * single_name() + single_name() + default_options();
+    exception.map(|((source, target), options)| Exception {
+        source,
+        target,
+        inherit_all: option(&options, "all"),
+        override_target: option(&options, "override"),
+    })
+}
+
+// `\DefaultInheritance[\except{book}{inbook}{all=false}]{all=true, override=false}`
+fn defaults() -> Parser<u8, Command> {
+    let exceptions = sym(b'[') * space() * exception().repeat(0..) - sym(b']') - space();
+    let defaults = seq(b"\\DefaultInheritance") * space() * exceptions.opt() + default_options();
+    defaults.map(|(exceptions, options)| {
+        Command::Default(exceptions.unwrap_or_default(), option(&options, "all"), option(&options, "override"))
+    })
+}
+
+// `[ignore={singletitle, uniquetitle}]`, which only matters to uniqueness
+// and so is left aside
+fn ignore() -> Parser<u8, ()> {
+    let value = names().discard() | name().discard();
+    let ignore = seq(b"ignore") * space() * sym(b'=') * space() * value;
+    (sym(b'[') * space() * list(ignore, sym(b',') * space()) - sym(b']') - space()).discard()
+}
+
+// `\DeclareDataInheritance{book}{inbook}{...}`
+fn declaration() -> Parser<u8, Command> {
+    let fields = sym(b'{') * space() * mapping().repeat(0..) - sym(b'}') - space();
+    let declaration = seq(b"\\DeclareDataInheritance") * space() * ignore().opt() * names() + names() + fields;
+    declaration.map(|((sources, targets), fields)| Command::Declare(Rule { sources, targets, fields }))
+}
+
+// keys given in a field such as `xdata = {a, b}`
+fn references(entry: &PreparedEntry, name: &str) -> Vec<String> {
+    let value = entry.fields.iter().find_map(|f| match *f {
+        Field::Unknown(ref n, ref value) if n == name => Some(value),
+        _ => None,
+    });
+    match value {
+        Some(value) => value.split(',').map(str::trim).filter(|k| !k.is_empty()).map(String::from).collect(),
+        None => vec![],
+    }
+}
+
+// the field under another name, which may be modelled differently
+fn rename(field: &Field, name: &str) -> Option<Field> {
+    match field.name() == name {
+        true => Some(field.clone()),
+        false => self::field(name, &writer::value(field), &Options::default()).ok(),
+    }
+}
+
+// adds a field inherited from a parent
+fn inherit(fields: &mut Vec<Field>, inherited: Field, override_target: bool) {
+    match fields.iter().position(|f| f.name() == inherited.name()) {
+        Some(i) if override_target => fields[i] = inherited,
+        Some(_) => (),
+        None => fields.push(inherited),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Unresolved,
+    Resolving,
+    Resolved,
+}
+
+struct Resolver<'a> {
+    rules: &'a Rules,
+    keys: HashMap<String, usize>,
+    // the same, by lowercase key
+    folded_keys: HashMap<String, usize>,
+    states: Vec<State>,
+    // entries being resolved, each inheriting from the next
+    stack: Vec<usize>,
+    errors: Vec<(usize, ErrorKind)>,
+}
+
+impl<'a> Resolver<'a> {
+    // entry with the given key, which BibTeX matches case-insensitively in
+    // references, though an exact match comes first
+    fn find(&self, key: &str) -> Option<usize> {
+        self.keys.get(key).or_else(|| self.folded_keys.get(&key.to_lowercase())).cloned()
+    }
+
+    // parent with the given key, once it has been resolved itself
+    fn parent(&mut self, entries: &mut [PreparedEntry], child: usize, key: &str) -> Option<usize> {
+        let parent = match self.find(key) {
+            Some(parent) => parent,
+            None => {
+                self.errors.push((child, ErrorKind::UndefinedEntry(key.to_string())));
+                return None;
+            }
+        };
+        match self.states[parent] {
+            State::Unresolved => self.resolve(entries, parent),
+            State::Resolving => {
+                let start = self.stack.iter().position(|&i| i == parent).unwrap_or(0);
+                let mut cycle: Vec<String> = self.stack[start..].iter().map(|&i| entries[i].key.clone()).collect();
+                cycle.push(key.to_string());
+                self.errors.push((child, ErrorKind::InheritanceCycle(cycle)));
+                return None;
+            }
+            State::Resolved => (),
+        }
+        Some(parent)
+    }
+
+    fn resolve(&mut self, entries: &mut [PreparedEntry], i: usize) {
+        self.states[i] = State::Resolving;
+        self.stack.push(i);
+        for key in references(&entries[i], "xdata") {
+            if let Some(parent) = self.parent(entries, i, &key) {
+                for f in entries[parent].fields.clone() {
+                    if !self.rules.is_excluded(f.name()) {
+                        inherit(&mut entries[i].fields, f, false);
+                    }
+                }
+            }
+        }
+        for key in references(&entries[i], "crossref") {
+            if let Some(parent) = self.parent(entries, i, &key) {
+                let source = entries[parent].entry_type.clone();
+                for f in entries[parent].fields.clone() {
+                    for (name, override_target) in self.rules.targets(&source, &entries[i].entry_type, f.name()) {
+                        if let Some(inherited) = rename(&f, &name) {
+                            inherit(&mut entries[i].fields, inherited, override_target);
+                        }
+                    }
+                }
+            }
+        }
+        // `xref` only points to its parent, whose fields are not inherited
+        for key in references(&entries[i], "xref") {
+            if self.find(&key).is_none() {
+                self.errors.push((i, ErrorKind::UndefinedEntry(key)));
+            }
+        }
+        self.stack.pop();
+        self.states[i] = State::Resolved;
+    }
+}
+
+/// Lets each entry inherit the fields of the entries it refers to with its
+/// `xdata` and `crossref` fields, parents being resolved before their
+/// children
+///
+/// Returns the errors and warnings, along with the indices of the entries
+/// they concern.
+pub fn resolve(entries: &mut [PreparedEntry], rules: &Rules) -> Vec<(usize, ErrorKind)> {
+    let mut keys = HashMap::new();
+    let mut folded_keys = HashMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        keys.entry(entry.key.clone()).or_insert(i);
+        folded_keys.entry(entry.key.to_lowercase()).or_insert(i);
+    }
+    let mut resolver = Resolver {
+        rules,
+        keys,
+        folded_keys,
+        states: vec![State::Unresolved; entries.len()],
+        stack: Vec::new(),
+        errors: Vec::new(),
+    };
+    for i in 0..entries.len() {
+        if resolver.states[i] == State::Unresolved {
+            resolver.resolve(entries, i);
+        }
+    }
+    resolver.errors
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn targets(rules: &Rules, source: &str, target: &str, name: &str) -> Vec<String> {
+        rules.targets(source, target, name).into_iter().map(|t| t.0).collect()
+    }
+
+    #[test]
+    fn default_rules() {
+        let rules = Rules::default();
+        assert_eq!(targets(&rules, "proceedings", "inproceedings", "title"), vec!["booktitle"]);
+        assert_eq!(targets(&rules, "book", "inbook", "author"), vec!["author", "bookauthor"]);
+        assert_eq!(targets(&rules, "mvbook", "inbook", "title"), vec!["maintitle"]);
+        assert_eq!(targets(&rules, "book", "inbook", "shorttitle"), Vec::<String>::new());
+        assert_eq!(targets(&rules, "proceedings", "inproceedings", "crossref"), Vec::<String>::new());
+        assert_eq!(rules.targets("misc", "misc", "title"), vec![("title".to_string(), false)]);
+        assert!(rules.is_excluded("ids"));
+        assert!(!rules.is_excluded("title"));
+    }
+
+    #[test]
+    fn configured_rules() {
+        let config = r"
+% rules of our style
+\DefaultInheritance[
+  \except{book}{inbook}{override=true}
+  \except{collection}{inbook}{all}
+]{all=false}
+\DeclareDataInheritance[ignore={singletitle, uniquetitle}]{book, collection}{inbook}{
+  \inherit{title}{maintitle}
+  \inherit[override=false]{date}{origdate}
+  \noinherit{note}
+}
+";
+        let rules = Rules::parse(config).unwrap();
+        assert!(!rules.inherit_all);
+        assert_eq!(rules.targets("book", "inbook", "title"), vec![("maintitle".to_string(), true)]);
+        assert_eq!(rules.targets("collection", "inbook", "title"), vec![("maintitle".to_string(), false)]);
+        assert_eq!(rules.targets("book", "inbook", "date"), vec![("origdate".to_string(), false)]);
+        assert_eq!(targets(&rules, "book", "inbook", "note"), Vec::<String>::new());
+        assert_eq!(targets(&rules, "book", "inbook", "publisher"), Vec::<String>::new());
+        assert_eq!(rules.targets("collection", "inbook", "publisher"), vec![("publisher".to_string(), false)]);
+        assert_eq!(targets(&rules, "book", "inbook", "author"), vec!["author", "bookauthor"]);
+
+        let rules = Rules::parse("\\ResetDataInheritance").unwrap();
+        assert_eq!(targets(&rules, "book", "inbook", "title"), vec!["title"]);
+        assert_eq!(targets(&rules, "book", "inbook", "crossref"), Vec::<String>::new());
+
+        let rules = Rules::parse("\\DeclareDataInheritance[ignore=uniquework]{book}{inbook}{\\noinherit{title}}").unwrap();
+        assert_eq!(targets(&rules, "book", "inbook", "title"), Vec::<String>::new());
+
+        let error = Rules::parse("\\DefaultInheritance{all=false}\n\\DeclareDataInheritance[override]{book}{inbook}{}").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 24));
+    }
+}
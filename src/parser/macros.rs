@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use parser::StringNode;
+use parser::values::{value, Piece, Value};
+use error::ErrorKind;
+
+// BibLaTeX (or rather Biber) defines the month macros as numbers, so that
+// `month = jan` ends up as `month = 1`
+const MONTHS: [(&str, &str); 12] = [
+    ("jan", "1"), ("feb", "2"), ("mar", "3"), ("apr", "4"),
+    ("may", "5"), ("jun", "6"), ("jul", "7"), ("aug", "8"),
+    ("sep", "9"), ("oct", "10"), ("nov", "11"), ("dec", "12"),
+];
+
+// journal abbreviations predefined by the standard BibTeX styles
+const JOURNALS: [(&str, &str); 20] = [
+    ("acmcs", "ACM Computing Surveys"),
+    ("acta", "Acta Informatica"),
+    ("cacm", "Communications of the ACM"),
+    ("ibmjrd", "IBM Journal of Research and Development"),
+    ("ibmsj", "IBM Systems Journal"),
+    ("ieeese", "IEEE Transactions on Software Engineering"),
+    ("ieeetc", "IEEE Transactions on Computers"),
+    ("ieeetcad", "IEEE Transactions on Computer-Aided Design of Integrated Circuits"),
+    ("ipl", "Information Processing Letters"),
+    ("jacm", "Journal of the ACM"),
+    ("jcss", "Journal of Computer and System Sciences"),
+    ("scp", "Science of Computer Programming"),
+    ("sicomp", "SIAM Journal on Computing"),
+    ("tocs", "ACM Transactions on Computer Systems"),
+    ("tods", "ACM Transactions on Database Systems"),
+    ("tog", "ACM Transactions on Graphics"),
+    ("toms", "ACM Transactions on Mathematical Software"),
+    ("toois", "ACM Transactions on Office Information Systems"),
+    ("toplas", "ACM Transactions on Programming Languages and Systems"),
+    ("tcs", "Theoretical Computer Science"),
+];
+
+/// Table of `@string` macros, mapping (case-insensitive) names to their values
+#[derive(Clone, Debug, Default)]
+pub struct Macros {
+    definitions: HashMap<String, String>,
+}
+
+impl Macros {
+    /// Table containing the month and journal macros every BibTeX style defines
+    pub fn predefined() -> Macros {
+        let mut macros = Macros::default();
+        for &(name, value) in MONTHS.iter().chain(JOURNALS.iter()) {
+            macros.define(name, value.to_string());
+        }
+        macros
+    }
+
+    /// Adds a macro to the table, replacing any previous definition
+    pub fn define(&mut self, name: &str, value: String) {
+        self.definitions.insert(name.to_lowercase(), value);
+    }
+
+    /// Expands a value and concatenates its pieces, failing if it refers to
+    /// an undefined macro (in which case the position of the reference is
+    /// returned along with the error)
+    pub fn expand(&self, value: &Value) -> Result<String, (usize, ErrorKind)> {
+        let mut expanded = String::new();
+        for piece in value {
+            match *piece {
+                Piece::Literal(ref s) => expanded.push_str(s),
+                Piece::Macro(ref name, position) => {
+                    match self.definitions.get(&name.to_lowercase()) {
+                        Some(s) => expanded.push_str(s),
+                        None => {
+                            return Err((position, ErrorKind::UndefinedMacro(name.clone())));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(expanded)
+    }
+}
+
+/// Lowers a macro definition, e.g. `@string{jacm = {Journal of the ACM}}`,
+/// from the syntax tree
+pub fn string_definition(node: &StringNode) -> (String, Value) {
+    (node.name.text.clone(), value(&node.value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pom::DataInput;
+    use parser::cst;
+
+    #[test]
+    fn definition() {
+        let mut data = DataInput::new(b"@String{ jacm = {Journal of the {ACM}} }");
+        let expected = (
+            "jacm".to_string(),
+            vec![Piece::Literal("Journal of the {ACM}".to_string())],
+        );
+        let node = cst::string_definition().parse(&mut data).unwrap();
+        assert_eq!(string_definition(&node), expected);
+    }
+
+    #[test]
+    fn expansion() {
+        let mut macros = Macros::predefined();
+        let month = vec![Piece::Macro("Mar".to_string(), 0)];
+        assert_eq!(macros.expand(&month), Ok("3".to_string()));
+
+        let journal = vec![
+            Piece::Literal("The ".to_string()),
+            Piece::Macro("jtac".to_string(), 10),
+        ];
+        let expected = (10, ErrorKind::UndefinedMacro("jtac".to_string()));
+        assert_eq!(macros.expand(&journal), Err(expected));
+
+        macros.define("JTAC", "Theory and Applications of Categories".to_string());
+        let expected = "The Theory and Applications of Categories".to_string();
+        assert_eq!(macros.expand(&journal), Ok(expected));
+    }
+}
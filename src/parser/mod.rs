@@ -1,48 +1,286 @@
-use pom::{DataInput, Parser, Error};
+use pom::{DataInput, Parser, Error as ParseError};
 use pom::parser::*;
 use pom::char_class::{space, multispace};
 use parser::ranges::ranges;
 use parser::names::name_list;
-use parser::fields::field;
-use parser::entries::entry;
-use biblatex::Entry;
+use parser::text::text;
+use parser::dates::date;
+use parser::fields::{legacy_year, month, raw_field, RawField};
+use parser::entries::{entry_key, prepare_entry, instantiate, raw_entry};
+use parser::inheritance::resolve;
+use parser::macros::{string_definition, Macros};
+use parser::values::{value, Value};
+use biblatex::Bibliography;
+use latex::flatten;
+use error::{Error, ErrorKind};
 
+pub use parser::fields::{field, canonical_name};
+pub use parser::inheritance::{Exception, Mapping, Rule, Rules};
+pub use parser::cst::{
+    Document, Item, Span, Token, EntryNode, FieldNode, StringNode, PreambleNode, ValueNode,
+};
+
+mod cst;
 mod fields;
 mod ranges;
 mod names;
 mod entries;
+mod macros;
+mod values;
+mod text;
+mod dates;
+mod inheritance;
 
 // spacing (zero or more)
 fn sp0() -> Parser<u8, ()> {
     is_a(space).repeat(0..).discard()
 }
 
-// spacing (one or more)
-fn sp1() -> Parser<u8, ()> {
-    is_a(space).repeat(1..).discard()
-}
-
 // spacing+newlines (zero or more)
 fn msp0() -> Parser<u8, ()> {
     is_a(multispace).repeat(0..).discard()
 }
 
-// spacing+newlines (zero or more)
-fn msp1() -> Parser<u8, ()> {
-    is_a(multispace).repeat(1..).discard()
+// recognises chars that can make up a field or macro name: any printable
+// ASCII character except for those with a special meaning in BibTeX
+fn identifier_char(c: u8) -> bool {
+    c > b' ' && c < 0x7f && !b"\"#%'(),={}".contains(&c)
+}
+
+// field or macro name, which cannot start with a digit
+fn identifier() -> Parser<u8, String> {
+    let first = is_a(|c| identifier_char(c) && !c.is_ascii_digit());
+    (first + is_a(identifier_char).repeat(0..)).collect().convert(String::from_utf8)
 }
 
-// match a braced literal expression, return its contents
+// match a braced literal expression, return its contents (including any
+// nested braces, which are needed to parse names correctly)
 fn literal() -> Parser<u8, String> {
-    let simple = none_of(b"{}").repeat(1..).convert(|bs| String::from_utf8(bs));
-    let nested = call(literal);
+    let simple = none_of(b"{}").repeat(1..).convert(String::from_utf8);
+    let nested = call(literal).map(|s| format!("{{{}}}", s));
     let content = (simple | nested).repeat(0..).map(|ref ss| ss.concat());
     sym(b'{') * content - sym(b'}')
 }
 
-pub fn parse_bib(buf: &[u8]) -> Result<Vec<(String, Option<Entry>)>, Error> {
-    let parser = (msp0() * entry() - msp0()).repeat(0..);
-    parser.parse(&mut DataInput::new(buf))
+/// Settings for how the values of fields are interpreted
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Options {
+    /// Whether to decode LaTeX accents, special characters, dashes and ties
+    /// into Unicode, in the titles, names and other text fields
+    pub decode_latex: bool,
+    /// Rules for inheriting fields through `crossref`, which default to
+    /// those of Biber
+    pub inheritance: Rules,
+    /// What to do with entries whose cite key is already taken
+    pub duplicates: Duplicates,
+}
+
+/// What to do with an entry whose cite key is that of an earlier entry
+///
+/// Keys which merely clash with the `ids` of another entry are reported as
+/// [`ErrorKind::AmbiguousKey`] errors instead, and the entry is kept.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Duplicates {
+    /// Keep the earlier entry, with a warning, as Biber does
+    #[default]
+    KeepFirst,
+    /// Replace the earlier entry in its place, with a warning
+    KeepLast,
+    /// Keep the earlier entry, and report the later one as an error
+    Error,
+}
+
+/// Parses a whole `.bib` file, failing at the first error; warnings are
+/// ignored
+///
+/// Macros defined with `@string` can be used by all the entries and
+/// preambles that follow them, along with the predefined month and journal
+/// macros.
+pub fn parse_bib(buf: &[u8]) -> Result<Bibliography, Error> {
+    parse_bib_with(buf, &Options::default())
+}
+
+/// Parses a whole `.bib` file with the given options, failing at the first
+/// error
+pub fn parse_bib_with(buf: &[u8], options: &Options) -> Result<Bibliography, Error> {
+    let (bibliography, errors) = parse_bib_lenient_with(buf, options);
+    match errors.into_iter().find(|e| !e.kind.is_warning()) {
+        Some(error) => Err(error),
+        None => Ok(bibliography),
+    }
+}
+
+/// Parses a whole `.bib` file, skipping over any malformed blocks or invalid
+/// entries
+///
+/// Returns everything which could be parsed, along with the errors for the
+/// rest and any warnings, in input order. After a syntax error, parsing
+/// resumes at the next line starting with `@`.
+pub fn parse_bib_lenient(buf: &[u8]) -> (Bibliography, Vec<Error>) {
+    parse_bib_lenient_with(buf, &Options::default())
+}
+
+/// Parses a whole `.bib` file with the given options, skipping over any
+/// malformed blocks or invalid entries
+pub fn parse_bib_lenient_with(buf: &[u8], options: &Options) -> (Bibliography, Vec<Error>) {
+    lower(&Document::parse(buf), buf, options)
+}
+
+// interprets the syntax tree of `buf`, expanding macros in document order,
+// then resolves inheritance between the entries before instantiating them
+fn lower(document: &Document, buf: &[u8], options: &Options) -> (Bibliography, Vec<Error>) {
+    let mut macros = Macros::predefined();
+    let mut bibliography = Bibliography::default();
+    let mut errors = Vec::new();
+    let mut prepared = Vec::new();
+
+    for item in &document.items {
+        match *item {
+            Item::String(ref node) => {
+                let (name, value) = string_definition(node);
+                match macros.expand(&value) {
+                    Ok(expanded) => macros.define(&name, expanded),
+                    Err((pos, kind)) => errors.push(Error::new(buf, pos, None, kind)),
+                }
+            }
+            Item::Preamble(ref node) => match macros.expand(&value(&node.value)) {
+                Ok(expanded) => bibliography.preambles.push(expanded),
+                Err((pos, kind)) => errors.push(Error::new(buf, pos, None, kind)),
+            },
+            Item::Entry(ref node) => {
+                let raw = raw_entry(node);
+                let key = raw.key.clone();
+                let mut warnings = Vec::new();
+                let entry = prepare_entry(raw, &macros, options, &mut warnings);
+                for (pos, kind) in warnings {
+                    errors.push(Error::new(buf, pos, Some(key.clone()), kind));
+                }
+                match entry {
+                    Ok(entry) => prepared.push(entry),
+                    Err((pos, kind)) => errors.push(Error::new(buf, pos, Some(key), kind)),
+                }
+            }
+            Item::Invalid(ref token) => errors.push(syntax_error(buf, token.span.start)),
+            Item::Comment(_) | Item::Junk(_) => (),
+        }
+    }
+
+    // entries are only validated once they have inherited their fields, and
+    // those caught in a cycle are left out
+    let mut failed = vec![false; prepared.len()];
+    for (i, kind) in resolve(&mut prepared, &options.inheritance) {
+        failed[i] |= !kind.is_warning();
+        errors.push(Error::new(buf, prepared[i].position, Some(prepared[i].key.clone()), kind));
+    }
+    for (entry, failed) in prepared.into_iter().zip(failed) {
+        let key = entry.key.clone();
+        let position = entry.position;
+        let mut warnings = Vec::new();
+        let entry = instantiate(entry, &mut warnings);
+        for (pos, kind) in warnings {
+            errors.push(Error::new(buf, pos, Some(key.clone()), kind));
+        }
+        let (key, entry) = match entry {
+            Ok(entry) if !failed => entry,
+            Ok(_) => continue,
+            Err((pos, kind)) => {
+                errors.push(Error::new(buf, pos, Some(key), kind));
+                continue;
+            }
+        };
+        if bibliography.contains_key(&key) {
+            let kind = match options.duplicates {
+                Duplicates::Error => ErrorKind::DuplicateKey(key.clone()),
+                Duplicates::KeepFirst | Duplicates::KeepLast => ErrorKind::DroppedDuplicate(key.clone()),
+            };
+            errors.push(Error::new(buf, position, Some(key.clone()), kind));
+            if options.duplicates != Duplicates::KeepLast {
+                continue;
+            }
+        }
+        // the entry is kept even if its keys are taken by other entries, in
+        // which case cite keys take precedence over `ids` and earlier `ids`
+        // over later ones
+        for id in Some(key.clone()).into_iter().chain(entry.ids()) {
+            match bibliography.resolve(&id) {
+                Some(other) if other != key => {
+                    let kind = ErrorKind::AmbiguousKey { key: id, entry: other.to_string() };
+                    errors.push(Error::new(buf, position, Some(key.clone()), kind));
+                }
+                _ => (),
+            }
+        }
+        bibliography.insert(&key, entry);
+    }
+    // errors are reported in input order
+    errors.sort_by_key(|e| e.offset);
+    (bibliography, errors)
+}
+
+// position of the next `@` at the start of a line (possibly indented), which
+// is where parsing resumes after the block at `position` turned out to be
+// malformed
+fn resync(buf: &[u8], position: usize) -> usize {
+    let start = position + buf[position..].iter().take_while(|&&c| multispace(c)).count();
+    let mut line_start = false;
+    for (i, &c) in buf.iter().enumerate().skip(start + 1) {
+        match c {
+            b'\n' => line_start = true,
+            b'@' if line_start => return i,
+            c if space(c) => (),
+            _ => line_start = false,
+        }
+    }
+    buf.len()
+}
+
+// position at which a parser failed
+fn failure_position(error: &ParseError, buf: &[u8]) -> usize {
+    match *error {
+        ParseError::Incomplete => buf.len(),
+        ParseError::Mismatch { position, .. } |
+        ParseError::Conversion { position, .. } |
+        ParseError::Expect { position, .. } |
+        ParseError::Custom { position, .. } => position,
+    }
+}
+
+// works out why the block starting at `position` could not be parsed
+fn syntax_error(buf: &[u8], position: usize) -> Error {
+    let start = position + buf[position..].iter().take_while(|&&c| multispace(c)).count();
+    let key = entry_key().parse(&mut DataInput { data: buf, position: start }).ok();
+
+    // an unterminated brace swallows the rest of the input, so we look for
+    // a brace in the block which is still open at the end
+    let mut open = Vec::new();
+    for (i, &c) in buf.iter().enumerate().skip(start) {
+        if c == b'{' {
+            open.push(i);
+        } else if c == b'}' && (open.pop().is_none() || open.is_empty()) {
+            break;
+        }
+    }
+    if let Some(&brace) = open.first() {
+        return Error::new(buf, brace, key, ErrorKind::UnterminatedBrace);
+    }
+
+    // otherwise, we report the furthest point any of the parsers reached
+    let blocks = [
+        cst::string_definition().discard(),
+        cst::preamble().discard(),
+        cst::comment().discard(),
+        cst::entry().discard(),
+    ];
+    let furthest = blocks.iter()
+        .filter_map(|p| p.parse(&mut DataInput { data: buf, position: start }).err())
+        .map(|e| failure_position(&e, buf))
+        .max()
+        .unwrap_or(start);
+    let message = match String::from_utf8_lossy(&buf[furthest..]).chars().next() {
+        Some(c) => format!("unexpected {:?}", c),
+        None => "unexpected end of input".to_string(),
+    };
+    Error::new(buf, furthest, key, ErrorKind::Syntax(message))
 }
 
 
@@ -50,6 +288,8 @@ pub fn parse_bib(buf: &[u8]) -> Result<Vec<(String, Option<Entry>)>, Error> {
 mod test {
     use super::*;
     use pom::DataInput;
+    use biblatex::{Article, Entry, Date, InProceedings, Text};
+    use writer;
 
     #[test]
     fn simple_literal() {
@@ -62,6 +302,313 @@ mod test {
     fn nested_literal() {
         let mut data = DataInput::new(b"{This is a {nested literal}}");
         let expected = "This is a nested literal".to_string();
-        assert_eq!(literal().parse(&mut data), Ok(expected));
+        assert_eq!(literal().parse(&mut data).map(|s| flatten(&s)), Ok(expected));
+    }
+
+    #[test]
+    fn macros() {
+        let raw = br#"@string{tac = {Theory and Applications of Categories}}
+
+@article{baez/article,
+  author       = {Baez, John C. and Lauda, Aaron D.},
+  title        = {Higher-Dimensional Algebra {V}: 2-Groups},
+  journaltitle = tac,
+  date         = 2004,
+  issue        = dec,
+}"#;
+        let entries: Vec<_> = parse_bib(raw).unwrap().into_iter().collect();
+        match entries[0].1 {
+            Entry::Article(Article { ref journal_title, ref issue, .. }) => {
+                assert_eq!(journal_title.plain(), "Theory and Applications of Categories");
+                assert_eq!(issue, &Some("12".to_string()));
+            }
+            ref other => panic!("unexpected entry {:?}", other),
+        }
+
+        let raw = b"@article{key, journaltitle = tac}";
+        let error = parse_bib(raw).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UndefinedMacro("tac".to_string()));
+        assert_eq!(error.offset, 29);
+        assert_eq!(error.key, Some("key".to_string()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn unknown_type() {
+        let raw = b"@misc{a, editor = {A}, title = {A}, date = 2001}\n@nonsense{b, title = {B}}";
+        let error = parse_bib(raw).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnknownEntryType("nonsense".to_string()));
+        assert_eq!((error.line, error.column), (2, 1));
+        assert_eq!(error.key, Some("b".to_string()));
+    }
+
+    #[test]
+    fn preamble_and_comments() {
+        let raw = br#"% This file was created with JabRef, contact: someone@example.com
+@string{ cmd = "\providecommand" }
+@preamble{ cmd # "{\noop}[1]{}" }
+
+Some text which BibTeX ignores.
+@comment{jabref-meta: databaseType:biblatex;}
+@comment This line is commented out, @article{...}
+@misc{a, editor = {Doe, Jane}, title = {A}, date = 2001}
+"#;
+        let bibliography = parse_bib(raw).unwrap();
+        assert_eq!(bibliography.preambles, vec!["\\providecommand{\\noop}[1]{}".to_string()]);
+        assert_eq!(bibliography.len(), 1);
+        assert_eq!(bibliography.entries()[0].0, "a");
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let raw = b"@misc{a, editor = {A}, title = {A, date = 2001}\n\n@misc{b, title = {B}}";
+        let error = parse_bib(raw).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnterminatedBrace);
+        assert_eq!((error.line, error.column), (1, 6));
+        assert_eq!(error.key, Some("a".to_string()));
+
+        let raw = b"@misc{a, editor = {A}, title = {A}, date = 2001}\n@misc{b, title = {B} junk}";
+        let error = parse_bib(raw).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Syntax("unexpected 'j'".to_string()));
+        assert_eq!((error.line, error.column), (2, 22));
+        assert_eq!(error.key, Some("b".to_string()));
+
+        let raw = b"@misc{a, editor = {A}, title = {A}, year = {n.d.}}";
+        let error = parse_bib(raw).unwrap_err();
+        let kind = ErrorKind::InvalidValue {
+            field: "year".to_string(),
+            value: "n.d.".to_string(),
+        };
+        assert_eq!(error.kind, kind);
+        assert_eq!(error.offset, 36);
+    }
+
+    #[test]
+    fn lenient() {
+        let raw = br#"@misc{a, editor = {A}, title = {A}, date = 2001}
+@misc{b, editor = {B}, title = {B} junk, date = 2002}
+  @misc{c, editor = {C}, title = {C}}
+@misc{d, editor = {D}, title = {D}, date = 2004}
+"#;
+        let (bibliography, errors) = parse_bib_lenient(raw);
+        let keys: Vec<&str> = bibliography.iter().map(|e| e.0.as_str()).collect();
+        assert_eq!(keys, vec!["a", "d"]);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].key, Some("b".to_string()));
+        assert_eq!(errors[0].kind, ErrorKind::Syntax("unexpected 'j'".to_string()));
+        assert_eq!(errors[1].key, Some("c".to_string()));
+        assert_eq!(errors[1].line, 3);
+
+        assert_eq!(parse_bib(raw), Err(errors[0].clone()));
+    }
+
+    #[test]
+    fn legacy_dates() {
+        let raw = br#"@misc{a, editor = {A}, title = {A}, year = 2004, month = mar}
+@misc{b, editor = {B}, title = {B}, year = 2004, month = {September}}
+@misc{c, editor = {C}, title = {C}, date = {2004-03-01}, year = 2004, month = 3}
+@misc{d, editor = {D}, title = {D}, date = {2005}, year = 2004}
+@misc{e, editor = {E}, title = {E}, year = 2004, month = {Spring}}
+"#;
+        let (bibliography, errors) = parse_bib_lenient(raw);
+        let dates: Vec<String> = bibliography.iter().map(|e| e.1.date().unwrap().to_string()).collect();
+        assert_eq!(dates, vec!["2004-03", "2004-09", "2004-03-01", "2005"]);
+
+        assert_eq!(errors.len(), 2);
+        let kind = ErrorKind::ConflictingDate {
+            date: Box::new(Date::year(2005)),
+            legacy: Box::new(Date::year(2004)),
+        };
+        assert_eq!(errors[0].kind, kind);
+        assert_eq!((errors[0].line, errors[0].key.as_deref()), (4, Some("d")));
+        assert!(errors[0].to_string().starts_with("warning: "));
+        assert_eq!(errors[1].key, Some("e".to_string()));
+        assert!(!errors[1].kind.is_warning());
+
+        let raw = b"@misc{d, editor = {D}, title = {D}, date = {2005}, year = 2004}";
+        assert_eq!(parse_bib(raw).unwrap().entries()[0].1.year(), Some(2005));
+    }
+
+    #[test]
+    fn decode_latex() {
+        let raw = br#"@article{godel,
+  author       = {G{\"o}del, Kurt and Fran\c{c}ois, Jean-Marie},
+  title        = {{\"U}ber formal unentscheidbare S{\"a}tze --- {I}},
+  journaltitle = {Monatshefte f{\"u}r Mathematik und Physik},
+  date         = 1931,
+  url          = {https://example.org/~godel},
+  note         = {\"U},
+}"#;
+        let options = Options { decode_latex: true, .. Options::default() };
+        let entries: Vec<_> = parse_bib_with(raw, &options).unwrap().into_iter().collect();
+        match entries[0].1 {
+            Entry::Article(Article { ref author, ref title, ref journal_title, ref url, ref extra, .. }) => {
+                assert_eq!(author.names[0].family, "Gödel");
+                assert_eq!(author.names[1].family, "François");
+                assert_eq!(title.plain(), "Über formal unentscheidbare Sätze — I");
+                assert_eq!(journal_title.plain(), "Monatshefte für Mathematik und Physik");
+                assert_eq!(url, &Some("https://example.org/~godel".to_string()));
+                assert_eq!(extra.get("note"), Some("\\\"U"));
+            }
+            ref other => panic!("unexpected entry {:?}", other),
+        }
+
+        let entries: Vec<_> = parse_bib(raw).unwrap().into_iter().collect();
+        match entries[0].1 {
+            Entry::Article(Article { ref title, .. }) => {
+                assert_eq!(title.plain(), "\\\"Uber formal unentscheidbare S\\\"atze --- I");
+            }
+            ref other => panic!("unexpected entry {:?}", other),
+        }
+    }
+
+    #[test]
+    fn crossref() {
+        let raw = br#"@inproceedings{talk,
+  author   = {Doe, Jane},
+  title    = {A Talk},
+  crossref = {proc2004},
+}
+@proceedings{proc2004,
+  editor    = {Roe, Richard},
+  title     = {Proceedings of the Conference},
+  shorttitle = {Proceedings},
+  date      = 2004,
+  crossref  = {series},
+}
+@mvproceedings{series,
+  editor = {Roe, Richard},
+  title  = {Conference Series},
+  date   = {2000/2010},
+}
+"#;
+        let entries: Vec<_> = parse_bib(raw).unwrap().into_iter().collect();
+        match entries[0].1 {
+            Entry::InProceedings(InProceedings { ref title, ref book_title, ref main_title, ref editor, ref date, ref extra, .. }) => {
+                assert_eq!(title.plain(), "A Talk");
+                assert_eq!(book_title.plain(), "Proceedings of the Conference");
+                assert_eq!(main_title.as_ref().map(|t| t.plain()), Some("Conference Series".to_string()));
+                assert_eq!(editor.as_ref().map(|e| e.names[0].family.as_str()), Some("Roe"));
+                assert_eq!(date, &Date::year(2004));
+                assert_eq!(extra.get("crossref"), Some("proc2004"));
+                assert_eq!(extra.get("shorttitle"), None);
+            }
+            ref other => panic!("unexpected entry {:?}", other),
+        }
+        assert_eq!(entries[1].1.extra().get("maintitle"), None);
+    }
+
+    #[test]
+    fn xdata() {
+        let raw = br#"@xdata{springer, publisher = {Springer}, location = {Berlin}}
+@book{a, author = {Doe, Jane}, title = {A}, date = 2001, location = {Heidelberg}, xdata = {springer}}
+@misc{b, editor = {B}, title = {B}, date = 2002, crossref = {nowhere}}
+"#;
+        let (bibliography, errors) = parse_bib_lenient(raw);
+        let book = &bibliography.entries()[1].1;
+        let fields: Vec<(String, String)> = book.fields().iter().map(|f| (f.name().to_string(), writer::value(f))).collect();
+        assert!(fields.contains(&("publisher".to_string(), "Springer".to_string())));
+        assert!(fields.contains(&("location".to_string(), "Heidelberg".to_string())));
+        assert_eq!(bibliography.len(), 3);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ErrorKind::UndefinedEntry("nowhere".to_string()));
+        assert!(errors[0].kind.is_warning());
+    }
+
+    #[test]
+    fn reference_case() {
+        let raw = br#"@inproceedings{talk, author = {Doe, Jane}, title = {A Talk}, crossref = {Proc}, xdata = {SPRINGER}}
+@proceedings{proc, editor = {Roe, Richard}, title = {Proceedings}, date = 2004}
+@xdata{springer, publisher = {Springer}}
+"#;
+        let (bibliography, errors) = parse_bib_lenient(raw);
+        assert_eq!(errors, vec![]);
+        let talk = bibliography.get("talk").unwrap();
+        assert_eq!(talk.book_title().map(Text::plain), Some("Proceedings".to_string()));
+        assert_eq!(talk.publisher(), Some("Springer"));
+    }
+
+    #[test]
+    fn inheritance_cycle() {
+        let raw = br#"@misc{a, editor = {A}, title = {A}, date = 2001, crossref = {b}}
+@misc{b, editor = {B}, title = {B}, date = 2002, crossref = {a}}
+@misc{c, editor = {C}, title = {C}, date = 2003}
+"#;
+        let (bibliography, errors) = parse_bib_lenient(raw);
+        let keys: Vec<&str> = bibliography.iter().map(|e| e.0.as_str()).collect();
+        assert_eq!(keys, vec!["a", "c"]);
+        assert_eq!(errors.len(), 1);
+        let kind = ErrorKind::InheritanceCycle(vec!["a".to_string(), "b".to_string(), "a".to_string()]);
+        assert_eq!(errors[0].kind, kind);
+        assert_eq!(errors[0].key, Some("b".to_string()));
+        assert_eq!(errors[0].line, 2);
+    }
+
+    #[test]
+    fn configured_inheritance() {
+        let raw = br#"@mvbook{works, author = {Doe, Jane}, title = {Collected Works}, date = 1990, note = {N}}
+@inbook{essay, title = {An Essay}, booktitle = {Essays}, date = 1991, crossref = {works}}
+"#;
+        let config = r"\DefaultInheritance[\except{mvbook}{inbook}{override=true}]{}
+\DeclareDataInheritance{mvbook}{inbook}{\inherit{date}{date} \noinherit{note}}";
+        let options = Options { inheritance: Rules::parse(config).unwrap(), .. Options::default() };
+        let entries: Vec<_> = parse_bib_with(raw, &options).unwrap().into_iter().collect();
+        let essay = &entries[1].1;
+        assert_eq!(essay.date(), Some(&Date::year(1990)));
+        assert!(essay.fields().iter().any(|f| f.name() == "maintitle"));
+        assert_eq!(essay.extra().get("note"), None);
+        assert_eq!(essay.extra().get("bookauthor"), Some("Doe, Jane"));
+
+        let entries: Vec<_> = parse_bib(raw).unwrap().into_iter().collect();
+        assert_eq!(entries[1].1.date(), Some(&Date::year(1991)));
+        assert_eq!(entries[1].1.extra().get("note"), Some("N"));
+    }
+
+    #[test]
+    fn duplicates() {
+        let raw = br#"@misc{a, editor = {A}, title = {First}, date = 2001, ids = {alias}}
+@misc{b, editor = {B}, title = {B}, date = 2002}
+@misc{a, editor = {A}, title = {Second}, date = 2003}
+@misc{alias, editor = {C}, title = {Third}, date = 2004}
+"#;
+        let title = |bibliography: &Bibliography, key| bibliography.get(key).and_then(|e| e.title()).map(Text::plain);
+        let (bibliography, errors) = parse_bib_lenient(raw);
+        assert_eq!(title(&bibliography, "a"), Some("First".to_string()));
+        assert_eq!(title(&bibliography, "alias"), Some("Third".to_string()));
+        assert_eq!(bibliography.len(), 3);
+        assert_eq!(errors.len(), 2);
+        assert_eq!((errors[0].line, &errors[0].kind), (3, &ErrorKind::DroppedDuplicate("a".to_string())));
+        let ambiguous = ErrorKind::AmbiguousKey { key: "alias".to_string(), entry: "a".to_string() };
+        assert_eq!((errors[1].line, &errors[1].kind), (4, &ambiguous));
+
+        // the second `a` takes the place of the first, without its `ids`
+        let options = Options { duplicates: Duplicates::KeepLast, .. Options::default() };
+        let (bibliography, errors) = parse_bib_lenient_with(raw, &options);
+        assert_eq!(bibliography.keys().collect::<Vec<_>>(), vec!["a", "b", "alias"]);
+        assert_eq!(title(&bibliography, "a"), Some("Second".to_string()));
+        assert_eq!(title(&bibliography, "alias"), Some("Third".to_string()));
+        assert_eq!(errors.len(), 1);
+
+        let options = Options { duplicates: Duplicates::Error, .. Options::default() };
+        let error = parse_bib_with(raw, &options).unwrap_err();
+        assert_eq!((error.kind, error.line), (ErrorKind::DuplicateKey("a".to_string()), 3));
+    }
+
+    #[test]
+    fn ambiguous_keys() {
+        let raw = br#"@misc{a, editor = {A}, title = {A}, date = 2001, ids = {x}}
+@misc{b, editor = {B}, title = {B}, date = 2002, ids = {a, y}}
+@misc{x, editor = {X}, title = {X}, date = 2003}
+"#;
+        let options = Options { duplicates: Duplicates::KeepLast, .. Options::default() };
+        let (bibliography, errors) = parse_bib_lenient_with(raw, &options);
+        assert_eq!(bibliography.keys().collect::<Vec<_>>(), vec!["a", "b", "x"]);
+        assert_eq!(bibliography.resolve("a"), Some("a"));
+        assert_eq!(bibliography.resolve("x"), Some("x"));
+        assert_eq!(bibliography.resolve("y"), Some("b"));
+        let kinds: Vec<_> = errors.iter().map(|e| (e.line, e.kind.to_string(), e.kind.is_warning())).collect();
+        assert_eq!(kinds, vec![
+            (2, "key `a` already refers to entry `a`".to_string(), false),
+            (3, "key `x` already refers to entry `a`".to_string(), false),
+        ]);
+    }
+}
@@ -1,63 +1,269 @@
 use pom::Parser;
 use pom::parser::*;
-use parser::{literal, sp0, sp1, msp0, msp1};
+use pom::char_class::multispace;
+use parser::literal;
 use biblatex::{Name, NameList};
 
-// utility function for the `name()` parser; takes the list of tokens before
-// the comma, and optionally the list of tokens after the comma, and identifies
-// the given name and the family name
-fn split_name(mut before: Vec<String>, after: Option<Vec<String>>) -> Name {
-    match after {
-        None => match before.len() {
-            0 => unreachable!(),
-            // no commas, only one token
-            1 => {
-                Name {
-                    family: before.pop().unwrap(),
-                    .. Name::default()
+// Names are split up the way BibTeX does it: a name is written either as
+// `First von Last`, `von Last, First` or `von Last, Jr, First`, and the von
+// part is made up of the words starting with a lowercase letter.
+
+// word of a name, along with the separator following it
+#[derive(Clone, Debug, PartialEq)]
+struct Word {
+    text: String,
+    separator: String,
+}
+
+enum Lexeme {
+    Word(Word),
+    Comma,
+}
+
+// word, with any braced groups in it kept as they are
+fn word() -> Parser<u8, String> {
+    let plain = none_of(b" \t\r\n~-,{}").discard();
+    let group = call(literal).discard();
+    (plain | group).repeat(1..).collect().convert(String::from_utf8)
+}
+
+// whitespace, ties and hyphens between words; hyphens and ties are kept
+// when joining the words back together, other whitespace becomes a space
+fn separator() -> Parser<u8, String> {
+    let separator = is_a(|c| multispace(c) || c == b'~' || c == b'-').repeat(0..);
+    separator.convert(String::from_utf8).map(|s| {
+        if s.contains('-') {
+            "-".to_string()
+        } else if s.contains('~') {
+            "~".to_string()
+        } else if s.is_empty() {
+            s
+        } else {
+            " ".to_string()
+        }
+    })
+}
+
+fn lexemes() -> Parser<u8, Vec<Lexeme>> {
+    let word = (word() + separator()).map(|(text, separator)| Lexeme::Word(Word { text, separator }));
+    let comma = sym(b',') * separator().map(|_| Lexeme::Comma);
+    separator() * (word | comma).repeat(0..) - end()
+}
+
+// case of a word, given by its first letter at brace level 0, or that of the
+// first special character (a braced group starting with a control sequence,
+// e.g. `{\'E}`); `None` for words without either
+fn is_lowercase(word: &str) -> Option<bool> {
+    let chars: Vec<char> = word.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '{' => {
+                let end = group_end(&chars, i);
+                if chars.get(i + 1) == Some(&'\\') {
+                    return special_case(&chars[i + 2..end]);
                 }
+                i = end;
             }
-            // no commas, multiple tokens: last is family
-            _ => {
-                Name {
-                    family: before.pop().unwrap(),   
-                    given: Some(before.join(" ")),
+            c if c.is_alphabetic() => return Some(c.is_lowercase()),
+            _ => (),
+        }
+        i += 1;
+    }
+    None
+}
+
+// position of the brace closing the group opened at `start`
+fn group_end(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    for (i, &c) in chars.iter().enumerate().skip(start) {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
                 }
             }
-        },
-        Some(after) => {
-            Name {
-                family: before.join(" "),
-                given: Some(after.join(" ")),
+            _ => (),
+        }
+    }
+    chars.len()
+}
+
+// case of a special character, given the text following its backslash:
+// control words such as `\AE` or `\ss` have the case of their name, accents
+// such as `\'e` that of the letter they apply to
+fn special_case(chars: &[char]) -> Option<bool> {
+    match chars.first() {
+        Some(c) if c.is_alphabetic() => Some(c.is_lowercase()),
+        _ => chars.iter().skip(1).find(|c| c.is_alphabetic()).map(|c| c.is_lowercase()),
+    }
+}
+
+// drops the outermost braces of each group
+fn strip_braces(text: &str) -> String {
+    let mut stripped = String::new();
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '{' => {
+                depth += 1;
+                if depth > 1 {
+                    stripped.push(c);
+                }
+            }
+            '}' => {
+                depth -= 1;
+                if depth > 0 {
+                    stripped.push(c);
+                }
+            }
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+// joins words back together, braces included
+fn raw(words: &[Word]) -> String {
+    let mut joined = String::new();
+    for (i, word) in words.iter().enumerate() {
+        joined.push_str(&word.text);
+        if i + 1 < words.len() {
+            joined.push_str(&word.separator);
+        }
+    }
+    joined
+}
+
+// joins words back together, dropping the outermost braces
+fn join(words: &[Word]) -> String {
+    strip_braces(&raw(words))
+}
+
+// splits `key=value` (at the first `=` outside of braces)
+fn key_value(part: &str) -> Option<(String, String)> {
+    let mut depth = 0;
+    for (i, c) in part.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            '=' if depth == 0 => {
+                let key = part[..i].trim();
+                let valid = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+                return match valid {
+                    true => Some((key.to_lowercase(), strip_braces(part[i + 1..].trim()))),
+                    false => None,
+                };
             }
+            _ => (),
         }
     }
+    None
 }
 
-// space-or-comma-delimited name token
-fn name_token() -> Parser<u8, String> {
-    let simple = none_of(b" ,{}\t\n\r\\").repeat(1..).convert(|bs| String::from_utf8(bs));
-    let sequence = (simple | literal()).repeat(1..);
-    sequence.map(|ss| ss.concat())
+// Biber's extended name format, e.g. `family=Doe, given=Jane, prefix=van,
+// useprefix=true`, in which every part is a `key=value` pair
+fn extended_name(parts: &[Vec<Word>]) -> Option<Name> {
+    let pairs = parts.iter()
+        .filter(|p| !p.is_empty())
+        .map(|p| key_value(&raw(p)))
+        .collect::<Option<Vec<(String, String)>>>()?;
+    let mut name = Name::default();
+    for (key, value) in pairs {
+        match key.as_str() {
+            "family" => name.family = value,
+            "given" => name.given = Some(value),
+            "prefix" => name.prefix = Some(value),
+            "suffix" => name.suffix = Some(value),
+            _ => name.options.push((key, value)),
+        }
+    }
+    Some(name)
 }
 
-// comma-delimited name part
-fn name_part() -> Parser<u8, Vec<String>> {
-    // TODO fix this ugly lookahead hack
-    list(name_token(), sp1() - !((seq(b"and") - msp1())| seq(b"}")))
+fn non_empty(s: String) -> Option<String> {
+    match s.is_empty() {
+        true => None,
+        false => Some(s),
+    }
 }
 
-// single name
-fn name() -> Parser<u8, Name> {
-    let name = name_part() + (sp0() * sym(b',') * sp0() * name_part()).opt();
-    name.map(|(b, a)| split_name(b, a))
+// splits `von Last` at the last lowercase word, the last word always being
+// part of the family name
+fn split_von_last(words: &[Word]) -> (Option<String>, String) {
+    let von_end = words[..words.len() - 1].iter()
+        .rposition(|w| is_lowercase(&w.text) == Some(true))
+        .map_or(0, |i| i + 1);
+    (non_empty(join(&words[..von_end])), join(&words[von_end..]))
+}
+
+// identifies the parts of a name, given the words between its commas
+fn split_name(mut parts: Vec<Vec<Word>>) -> Option<Name> {
+    if parts[0].is_empty() {
+        return None;
+    }
+    if let Some(name) = extended_name(&parts) {
+        return Some(name);
+    }
+    if parts.len() == 1 {
+        // `First von Last`: the von part runs from the first lowercase word
+        // to the last one
+        let words = parts.remove(0);
+        let last = words.len() - 1;
+        let (given, prefix, family) = match words[..last].iter().position(|w| is_lowercase(&w.text) == Some(true)) {
+            Some(start) => {
+                let (prefix, family) = split_von_last(&words[start..]);
+                (join(&words[..start]), prefix, family)
+            }
+            None => (join(&words[..last]), None, join(&words[last..])),
+        };
+        return Some(Name { family, given: non_empty(given), prefix, .. Name::default() });
+    }
+
+    let (prefix, family) = split_von_last(&parts[0]);
+    let (suffix, given) = match parts.len() {
+        // `von Last, First`
+        2 => (None, join(&parts[1])),
+        // `von Last, Jr, First`; BibTeX complains about any further commas,
+        // we keep them in the given name
+        _ => {
+            let given: Vec<String> = parts[2..].iter().map(|p| join(p)).collect();
+            (non_empty(join(&parts[1])), given.join(", "))
+        }
+    };
+    Some(Name { family, given: non_empty(given), prefix, suffix, options: Vec::new() })
+}
+
+// splits the lexemes into names at each `and`, and the names at their
+// commas; a trailing (unbraced) `others` means the list is truncated
+fn split_names(lexemes: Vec<Lexeme>) -> Result<NameList, &'static str> {
+    if lexemes.is_empty() {
+        return Ok(NameList::default());
+    }
+    let mut names = vec![vec![Vec::new()]];
+    for lexeme in lexemes {
+        match lexeme {
+            Lexeme::Word(ref w) if w.text.eq_ignore_ascii_case("and") => names.push(vec![Vec::new()]),
+            Lexeme::Word(w) => names.last_mut().unwrap().last_mut().unwrap().push(w),
+            Lexeme::Comma => names.last_mut().unwrap().push(Vec::new()),
+        }
+    }
+    let truncated = match names.last().unwrap().as_slice() {
+        [words] if words.len() == 1 => words[0].text.eq_ignore_ascii_case("others"),
+        _ => false,
+    };
+    if truncated {
+        names.pop();
+    }
+    let names = names.into_iter().map(split_name).collect::<Option<Vec<Name>>>().ok_or("empty name")?;
+    Ok(NameList { names, truncated })
 }
 
 /// List of names, used in fields such as `author` and `editor`.
 pub fn name_list() -> Parser<u8, NameList> {
-    let names = list(name(), msp1() * seq(b"and") - msp1());
-    let delimited = sym(b'{') * msp0() * names - msp0() - sym(b'}');
-    delimited.map(|ns| NameList::from_names(ns))
+    lexemes().convert(split_names)
 }
 
 #[cfg(test)]
@@ -65,56 +271,131 @@ mod test {
     use super::*;
     use pom::DataInput;
 
+    fn name(given: Option<&str>, prefix: Option<&str>, family: &str, suffix: Option<&str>) -> Name {
+        Name {
+            family: family.to_string(),
+            given: given.map(|s| s.to_string()),
+            prefix: prefix.map(|s| s.to_string()),
+            suffix: suffix.map(|s| s.to_string()),
+            .. Name::default()
+        }
+    }
+
+    fn parse_name(raw: &str) -> Name {
+        let names = name_list().parse(&mut DataInput::new(raw.as_bytes())).unwrap().names;
+        assert_eq!(names.len(), 1);
+        names[0].clone()
+    }
+
     #[test]
-    fn single() {
-        let mut data = DataInput::new(b"{'t Hooft}, Gerard and  junk");
-        let expected = Name {
-            family: "'t Hooft".to_string(),
-            given: Some("Gerard".to_string()),
-        };
-        assert_eq!(name().parse(&mut data), Ok(expected));
+    fn first_von_last() {
+        let beethoven = name(Some("Ludwig"), Some("van"), "Beethoven", None);
+        assert_eq!(parse_name("Ludwig van Beethoven"), beethoven);
+        assert_eq!(parse_name("van Beethoven, Ludwig"), beethoven);
 
-        let mut data = DataInput::new(b"Gerard {'t Hooft}");
-        let expected = Name {
-            family: "'t Hooft".to_string(),
-            given: Some("Gerard".to_string()),
-        };
-        assert_eq!(name().parse(&mut data), Ok(expected));
+        assert_eq!(parse_name("Charles Louis Xavier Joseph de la Vall{\\'e}e Poussin"),
+                   name(Some("Charles Louis Xavier Joseph"), Some("de la"), "Vall\\'ee Poussin", None));
+        assert_eq!(parse_name("Per Brinch Hansen"), name(Some("Per Brinch"), None, "Hansen", None));
+        assert_eq!(parse_name("Brinch Hansen, Per"), name(Some("Per"), None, "Brinch Hansen", None));
+        assert_eq!(parse_name("Jean-Paul Sartre"), name(Some("Jean-Paul"), None, "Sartre", None));
+        assert_eq!(parse_name("Donald~E. Knuth"), name(Some("Donald~E."), None, "Knuth", None));
+        assert_eq!(parse_name("jean de la fontaine"), name(None, Some("jean de la"), "fontaine", None));
+        assert_eq!(parse_name("Aristotle"), name(None, None, "Aristotle", None));
+    }
+
+    #[test]
+    fn von_detection() {
+        // examples from "Tame the BeaST"
+        assert_eq!(parse_name("Jean de La Fontaine"), name(Some("Jean"), Some("de"), "La Fontaine", None));
+        assert_eq!(parse_name("Jean De La Fontaine"), name(Some("Jean De La"), None, "Fontaine", None));
+        assert_eq!(parse_name("Jean {de} La Fontaine"), name(Some("Jean de La"), None, "Fontaine", None));
+        assert_eq!(parse_name("De La Fontaine, Jean"), name(Some("Jean"), None, "De La Fontaine", None));
+        assert_eq!(parse_name("de La Fontaine, Jean"), name(Some("Jean"), Some("de"), "La Fontaine", None));
+        assert_eq!(parse_name("Jean {\\relax de} La Fontaine"), name(Some("Jean"), Some("\\relax de"), "La Fontaine", None));
+        assert_eq!(parse_name("{\\'E}mile Zola"), name(Some("\\'Emile"), None, "Zola", None));
+        assert_eq!(parse_name("Kurt G{\\\"o}del"), name(Some("Kurt"), None, "G\\\"odel", None));
+    }
+
+    #[test]
+    fn jr() {
+        assert_eq!(parse_name("Smith, Jr., John"), name(Some("John"), None, "Smith", Some("Jr.")));
+        assert_eq!(parse_name("Ford, Jr, Henry"), name(Some("Henry"), None, "Ford", Some("Jr")));
+        assert_eq!(parse_name("von Ford, Jr, Henry"), name(Some("Henry"), Some("von"), "Ford", Some("Jr")));
+        assert_eq!(parse_name("Doe,, Jane"), name(Some("Jane"), None, "Doe", None));
     }
 
     #[test]
     fn list() {
-        let name1 = Name {
-            family: "'t Hooft".to_string(),
-            given: Some("Gerard".to_string()),
-        };
-        let name2 = Name {
-            family: "Celentano".to_string(),
-            given: Some("A. Driano".to_string()),
-        };
+        let hooft = name(Some("Gerard"), Some("'t"), "Hooft", None);
+        let celentano = name(Some("A. Driano"), None, "Celentano", None);
         let expected = NameList {
-            names: vec![name1, name2],
+            names: vec![hooft.clone(), celentano],
             truncated: false,
         };
 
         // comma-separated
-        let mut data = DataInput::new(b"{'t Hooft, Gerard and Celentano, A. Driano}");
+        let mut data = DataInput::new(b"'t Hooft, Gerard and Celentano, A. Driano");
         assert_eq!(name_list().parse(&mut data), Ok(expected.clone()));
 
         // mixed
-        let mut data = DataInput::new(b"{'t Hooft, Gerard and A. Driano Celentano}");
-        assert_eq!(name_list().parse(&mut data), Ok(expected.clone()));
-
-        // no commas
-        let mut data = DataInput::new(b"{Gerard {'t Hooft} and A. Driano Celentano}");
+        let mut data = DataInput::new(b"Gerard 't Hooft and A. Driano Celentano");
         assert_eq!(name_list().parse(&mut data), Ok(expected.clone()));
 
         // newline
-        let mut data = DataInput::new(b"{Gerard {'t Hooft} and\n Celentano, A. Driano }");
+        let mut data = DataInput::new(b"Gerard 't Hooft and\n Celentano, A. Driano ");
         assert_eq!(name_list().parse(&mut data), Ok(expected.clone()));
 
         // weird spacing
-        let mut data = DataInput::new(b"{ 't Hooft,Gerard \n  and  Celentano  , A.  Driano }");
+        let mut data = DataInput::new(b" 't Hooft,Gerard \n  AND  Celentano  , A.  Driano ");
         assert_eq!(name_list().parse(&mut data), Ok(expected));
+
+        // braces keep words together
+        let hooft = Name { family: "'t Hooft".to_string(), prefix: None, .. hooft };
+        let mut data = DataInput::new(b"Gerard {'t Hooft} and {Barnes and Noble, Inc.}");
+        let expected = NameList {
+            names: vec![hooft, name(None, None, "Barnes and Noble, Inc.", None)],
+            truncated: false,
+        };
+        assert_eq!(name_list().parse(&mut data), Ok(expected));
+
+        assert!(name_list().parse(&mut DataInput::new(b"Doe and and Roe")).is_err());
+    }
+
+    #[test]
+    fn extended() {
+        let mut expected = name(Some("Jane"), Some("van"), "Doe", None);
+        expected.options = vec![("useprefix".to_string(), "true".to_string())];
+        assert_eq!(parse_name("family=Doe, given=Jane, prefix=van, useprefix=true"), expected);
+        assert_eq!(parse_name("given = {Jane}, Family={Doe}, prefix=van, useprefix=true"), expected);
+
+        let expected = name(Some("Jean-Paul"), None, "Doe, Jr", Some("III"));
+        assert_eq!(parse_name("family={Doe, Jr}, given={Jean-Paul}, suffix=III"), expected);
+
+        let mut data = DataInput::new(b"family=Doe, given=Jane and Roe, Richard and given=Jo, family={Smith and Sons}");
+        let list = name_list().parse(&mut data).unwrap();
+        let families: Vec<&str> = list.names.iter().map(|n| n.family.as_str()).collect();
+        assert_eq!(families, vec!["Doe", "Roe", "Smith and Sons"]);
+
+        // an `=` in an ordinary name does not make it an extended one
+        assert_eq!(parse_name("Doe, Jane=Jo"), name(Some("Jane=Jo"), None, "Doe", None));
+    }
+
+    #[test]
+    fn others() {
+        let mut data = DataInput::new(b"Doe, Jane and Roe, Richard and Others");
+        let list = name_list().parse(&mut data).unwrap();
+        assert_eq!(list.names.len(), 2);
+        assert!(list.truncated);
+        assert_eq!(list.to_string(), "Doe, Roe et al.");
+
+        // only a trailing, unbraced `others` truncates the list
+        let mut data = DataInput::new(b"Doe, Jane and {others}");
+        let list = name_list().parse(&mut data).unwrap();
+        assert_eq!(list.names[1].family, "others");
+        assert!(!list.truncated);
+        assert_eq!(list.to_string(), "Doe, others");
+
+        let mut data = DataInput::new(b"others and Doe, Jane");
+        assert!(!name_list().parse(&mut data).unwrap().truncated);
     }
 }
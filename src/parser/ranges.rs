@@ -6,7 +6,7 @@ use biblatex::Range;
 fn range() -> Parser<u8, Range> {
     let token = || sp0() * none_of(b",-{}\n ")
         .repeat(1..)
-        .convert(|bs| String::from_utf8(bs)) - sp0();
+        .convert(String::from_utf8) - sp0();
     let start = token() - sp0();
     let end = (sym(b'-').repeat(1..) * sp0() * token()).opt();
     let range = start - sp0() + end;
@@ -14,7 +14,7 @@ fn range() -> Parser<u8, Range> {
 }
 
 pub fn ranges() -> Parser<u8, Vec<Range>> {
-    sym(b'{') * sp0() * list(range(), sp0() * sym(b',') - sp0()) - sp0() - sym(b'}')
+    sp0() * list(range(), sp0() * sym(b',') - sp0()) - sp0()
 }
 
 #[cfg(test)]
@@ -51,10 +51,10 @@ mod test {
         };
         let expected = vec![expected1, expected2];
 
-        let mut data1 = DataInput::new(b"{1-7, 10--14 }");
+        let mut data1 = DataInput::new(b"1-7, 10--14 ");
         assert_eq!(ranges().parse(&mut data1), Ok(expected.clone()));
 
-        let mut data2 = DataInput::new(b"{ 1 -7 , 10-- 14 }");
+        let mut data2 = DataInput::new(b" 1 -7 , 10-- 14 ");
         assert_eq!(ranges().parse(&mut data2), Ok(expected));
     }
 }
\ No newline at end of file
@@ -0,0 +1,122 @@
+use pom::Parser;
+use pom::parser::*;
+use parser::literal;
+use biblatex::{Chunk, Text};
+
+// braced group, which protects its contents unless it starts with a command,
+// in which case it is a special character such as `{\'E}`
+fn group() -> Parser<u8, Chunk> {
+    literal().map(|s| match s.starts_with('\\') {
+        true => Chunk::Normal(format!("{{{}}}", s)),
+        false => Chunk::Protected(s),
+    })
+}
+
+// a backslash along with the character it escapes, e.g. `\$` or `\{`
+fn escape() -> Parser<u8, Vec<u8>> {
+    (sym(b'\\') * is_a(|_| true).opt()).collect()
+}
+
+fn math() -> Parser<u8, Chunk> {
+    let content = (escape() | none_of(b"$\\").map(|c| vec![c])).repeat(0..);
+    (sym(b'$') * content.map(|pieces| pieces.concat()).convert(String::from_utf8) - sym(b'$')).map(Chunk::Math)
+}
+
+// plain text, or a `$` which isn't closed; escaped braces and `$` signs are
+// part of the text
+fn normal() -> Parser<u8, Chunk> {
+    let text = (escape() | none_of(b"{}$\\").map(|c| vec![c])).repeat(1..).map(|pieces| pieces.concat());
+    (text | sym(b'$').map(|c| vec![c])).convert(String::from_utf8).map(Chunk::Normal)
+}
+
+/// Rich text, e.g. `The {DNA} of $E. coli$`
+pub fn text() -> Parser<u8, Text> {
+    (group() | math() | normal()).repeat(0..).map(|chunks| {
+        // special characters and the text around them form a single chunk
+        let mut merged: Vec<Chunk> = Vec::new();
+        for chunk in chunks {
+            match (merged.last_mut(), chunk) {
+                (Some(&mut Chunk::Normal(ref mut last)), Chunk::Normal(ref s)) => last.push_str(s),
+                (_, chunk) => merged.push(chunk),
+            }
+        }
+        Text::new(merged)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pom::DataInput;
+    use biblatex::Case;
+
+    fn parse_text(raw: &[u8]) -> Text {
+        (text() - end()).parse(&mut DataInput::new(raw)).unwrap()
+    }
+
+    #[test]
+    fn protected() {
+        let expected = Text::new(vec![
+            Chunk::Normal("Higher-Dimensional Algebra ".to_string()),
+            Chunk::Protected("V".to_string()),
+            Chunk::Normal(": 2-Groups".to_string()),
+        ]);
+        let parsed = parse_text(b"Higher-Dimensional Algebra {V}: 2-Groups");
+        assert_eq!(parsed, expected);
+        assert_eq!(parsed.plain(), "Higher-Dimensional Algebra V: 2-Groups");
+        assert_eq!(parsed.to_latex(), "Higher-Dimensional Algebra {V}: 2-Groups");
+
+        let parsed = parse_text(b"{The {DNA}} of {\\\"U}ber-{\\AE}sop");
+        let expected = Text::new(vec![
+            Chunk::Protected("The {DNA}".to_string()),
+            Chunk::Normal(" of {\\\"U}ber-{\\AE}sop".to_string()),
+        ]);
+        assert_eq!(parsed, expected);
+        assert_eq!(parsed.plain(), "The DNA of \\\"Uber-\\AEsop");
+    }
+
+    #[test]
+    fn math() {
+        let parsed = parse_text(b"Groups of order $p^{2}$ cost $5");
+        let expected = Text::new(vec![
+            Chunk::Normal("Groups of order ".to_string()),
+            Chunk::Math("p^{2}".to_string()),
+            Chunk::Normal(" cost $5".to_string()),
+        ]);
+        assert_eq!(parsed, expected);
+        assert_eq!(parsed.plain(), "Groups of order $p^{2}$ cost $5");
+
+        let parsed = parse_text(b"Costs \\$5 and \\$10 for $\\{x\\}$ in \\{a\\}");
+        let expected = Text::new(vec![
+            Chunk::Normal("Costs \\$5 and \\$10 for ".to_string()),
+            Chunk::Math("\\{x\\}".to_string()),
+            Chunk::Normal(" in \\{a\\}".to_string()),
+        ]);
+        assert_eq!(parsed, expected);
+        assert_eq!(parsed.plain(), "Costs $5 and $10 for $\\{x\\}$ in {a}");
+        assert_eq!(parsed.to_latex(), "Costs \\$5 and \\$10 for $\\{x\\}$ in \\{a\\}");
+    }
+
+    #[test]
+    fn bibtex_case() {
+        let title = parse_text(b"The {DNA} of {\\AE}sop: {\\'E}tudes on $\\mathcal{O}$ and \\LaTeX");
+        let case = |case| title.to_case(case).to_latex();
+        assert_eq!(case(Case::Lower), "the {DNA} of {\\ae}sop: {\\'e}tudes on $\\mathcal{O}$ and \\LaTeX");
+        assert_eq!(case(Case::Upper), "THE {DNA} OF {\\AE}SOP: {\\'E}TUDES ON $\\mathcal{O}$ AND \\LaTeX");
+        assert_eq!(case(Case::Sentence), "The {DNA} of {\\ae}sop: {\\'E}tudes on $\\mathcal{O}$ and \\LaTeX");
+
+        let title = parse_text(b"Stra{\\ss}e und Ma{\\\"\\i}s:Mehr");
+        assert_eq!(title.to_case(Case::Upper).to_latex(), "STRA{SS}E UND MA{\\\"I}S:MEHR");
+        assert_eq!(title.to_case(Case::Sentence).to_latex(), "Stra{\\ss}e und ma{\\\"\\i}s:mehr");
+    }
+
+    #[test]
+    fn title_case() {
+        let title = parse_text(b"a theory of {iPhone} use in higher-dimensional algebra: on the \"state of the art\" of it");
+        let expected = "A Theory of {iPhone} Use in Higher-Dimensional Algebra: On the \"State of the Art\" of It";
+        assert_eq!(title.to_case(Case::Title).to_latex(), expected);
+
+        let title = parse_text(b"{\\'e}tudes AND {DNA}s $x$ -- by the way");
+        assert_eq!(title.to_case(Case::Title).to_latex(), "{\\'E}tudes and {DNA}s $x$ -- by the Way");
+    }
+}
@@ -0,0 +1,64 @@
+use parser::{Token, ValueNode};
+
+/// Part of a field value, several of which can be concatenated with `#`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Piece {
+    /// Braced or quoted literal, or bare number, e.g. `{Some Title}` or `2004`
+    Literal(String),
+    /// Reference to a `@string` macro, along with its position in the input
+    Macro(String, usize),
+}
+
+/// Field value as written in the input, before macro expansion
+pub type Value = Vec<Piece>;
+
+// strips the delimiters from a piece of a value
+fn piece(token: &Token) -> Piece {
+    let text = &token.text;
+    match text.as_bytes()[0] {
+        b'{' | b'"' => Piece::Literal(text[1..text.len() - 1].to_string()),
+        c if c.is_ascii_digit() => Piece::Literal(text.clone()),
+        _ => Piece::Macro(text.clone(), token.span.start),
+    }
+}
+
+/// Lowers a value from the syntax tree
+pub fn value(node: &ValueNode) -> Value {
+    node.pieces().map(piece).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pom::DataInput;
+    use parser::cst;
+
+    fn parse_value(raw: &[u8]) -> Value {
+        value(&cst::value().parse(&mut DataInput::new(raw)).unwrap())
+    }
+
+    #[test]
+    fn pieces() {
+        let expected = Piece::Literal("A {Nested} Literal".to_string());
+        assert_eq!(parse_value(b"{A {Nested} Literal}"), vec![expected]);
+
+        let expected = Piece::Literal("A {\"Quoted\"} Literal".to_string());
+        assert_eq!(parse_value(b"\"A {\"Quoted\"} Literal\""), vec![expected]);
+
+        let expected = Piece::Literal("2004".to_string());
+        assert_eq!(parse_value(b"2004"), vec![expected]);
+
+        let expected = Piece::Macro("jacm".to_string(), 2);
+        assert_eq!(parse_value(b"  jacm"), vec![expected]);
+    }
+
+    #[test]
+    fn concatenation() {
+        let expected = vec![
+            Piece::Macro("jan".to_string(), 0),
+            Piece::Literal("~1".to_string()),
+            Piece::Literal("st".to_string()),
+        ];
+        assert_eq!(parse_value(b"jan # \"~1\" #\n {st}"), expected);
+    }
+}
@@ -0,0 +1,454 @@
+use biblatex::{Bibliography, Entry, Field, Name, NameList, Range};
+use latex::{self, Fallback};
+
+/// Syntax in which entries are written
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dialect {
+    BibLaTeX,
+    /// Legacy BibTeX: entry types and field names which classic BibTeX
+    /// styles do not know are mapped onto the closest ones they do
+    BibTeX,
+}
+
+/// Delimiters around field values
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Delimiter {
+    /// `{value}`
+    Braces,
+    /// `"value"`, falling back to braces for values containing a `"`
+    Quotes,
+}
+
+// order in which fields are written by default
+const FIELD_ORDER: [&str; 31] = [
+    "author", "editor", "title", "maintitle", "booktitle", "journaltitle",
+    "journal", "series", "volume", "number", "issue", "edition", "chapter",
+    "pages", "type", "institution", "school", "organization", "publisher",
+    "location", "address", "date", "year", "month", "eventdate", "origdate", "doi",
+    "eprint", "url", "urldate", "entryset",
+];
+
+// macros for the months, which BibTeX styles expect instead of numbers
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+// fields whose values are identifiers rather than text, and so are never
+// encoded as LaTeX
+const VERBATIM: [&str; 4] = ["url", "doi", "eprint", "file"];
+
+/// Renders entries as `.bib` text
+#[derive(Clone, Debug, PartialEq)]
+pub struct Writer {
+    pub dialect: Dialect,
+    /// Fields which are written first, in this order, by their name in the
+    /// output; the others follow in the order in which the entry has them
+    pub field_order: Vec<String>,
+    /// Indentation of the fields
+    pub indent: String,
+    /// Whether to pad field names so that the `=` signs line up
+    pub align_equals: bool,
+    pub delimiter: Delimiter,
+    /// Whether to put a comma after the last field
+    pub trailing_comma: bool,
+    /// If set, characters which are not ASCII are written as LaTeX commands
+    /// (e.g. `{\"o}` for `ö`), for the benefit of 8-bit BibTeX; characters
+    /// which have no LaTeX equivalent are handled according to the fallback
+    pub encode_latex: Option<Fallback>,
+    /// Whether to write fields given under a legacy alias, such as
+    /// `journal`, under their BibLaTeX name instead, and every field in
+    /// lowercase; the BibTeX dialect always uses the BibTeX names, so there
+    /// this only affects their case
+    pub canonical_names: bool,
+}
+
+impl Default for Writer {
+    fn default() -> Writer {
+        Writer {
+            dialect: Dialect::BibLaTeX,
+            field_order: FIELD_ORDER.iter().map(|f| f.to_string()).collect(),
+            indent: "  ".to_string(),
+            align_equals: true,
+            delimiter: Delimiter::Braces,
+            trailing_comma: true,
+            encode_latex: None,
+            canonical_names: false,
+        }
+    }
+}
+
+impl Writer {
+    /// Renders the preambles and entries of a bibliography, separated by
+    /// blank lines
+    pub fn bibliography(&self, bibliography: &Bibliography) -> String {
+        let mut items = Vec::new();
+        for preamble in &bibliography.preambles {
+            items.push(format!("@preamble{{{}}}\n", self.delimit(preamble)));
+        }
+        for (key, entry) in bibliography {
+            items.push(self.entry(key, entry) + "\n");
+        }
+        items.join("\n")
+    }
+
+    /// Renders a single entry
+    pub fn entry(&self, key: &str, entry: &Entry) -> String {
+        let (entry_type, mut fields) = match self.dialect {
+            Dialect::BibLaTeX => (entry.entry_type(), biblatex_fields(entry, self.canonical_names)),
+            Dialect::BibTeX => (bibtex_type(entry), bibtex_fields(entry, self.canonical_names)),
+        };
+        fields.sort_by_key(|(name, _)| {
+            self.field_order.iter().position(|f| f.eq_ignore_ascii_case(name)).unwrap_or(self.field_order.len())
+        });
+
+        let width = match self.align_equals {
+            true => fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0),
+            false => 0,
+        };
+        let mut out = format!("@{}{{{},\n", entry_type, key);
+        for (i, (name, value)) in fields.iter().enumerate() {
+            let value = match self.encode_latex {
+                Some(ref fallback) if !VERBATIM.contains(&name.as_str()) => latex::encode(value, fallback),
+                _ => value.clone(),
+            };
+            // month macros are written as they are, without delimiters
+            let value = match name.eq_ignore_ascii_case("month") && MONTHS.contains(&value.as_str()) {
+                true => value,
+                false => self.delimit(&value),
+            };
+            out.push_str(&format!("{}{:width$} = {}", self.indent, name, value, width = width));
+            if self.trailing_comma || i + 1 < fields.len() {
+                out.push(',');
+            }
+            out.push('\n');
+        }
+        out.push('}');
+        out
+    }
+
+    fn delimit(&self, value: &str) -> String {
+        match self.delimiter {
+            Delimiter::Quotes if !value.contains('"') => format!("\"{}\"", value),
+            _ => format!("{{{}}}", value),
+        }
+    }
+}
+
+/// Renders a name list in the form `Family, Given and Family, Given`
+pub fn name_list(names: &NameList) -> String {
+    let mut parts: Vec<String> = names.names.iter().map(name).collect();
+    if names.truncated {
+        parts.push("others".to_string());
+    }
+    parts.join(" and ")
+}
+
+// `von Last, Jr, First`, or just `von Last`; names with options are written
+// in the extended name format
+fn name(name: &Name) -> String {
+    if !name.options.is_empty() {
+        return extended_name(name);
+    }
+    let alone = name.given.is_none() && name.suffix.is_none();
+    let mut written = match name.prefix {
+        Some(ref prefix) => format!("{} {}", prefix, protect(&name.family, alone)),
+        None => protect(&name.family, alone),
+    };
+    if let Some(ref suffix) = name.suffix {
+        written.push_str(", ");
+        written.push_str(suffix);
+    }
+    if let Some(ref given) = name.given {
+        written.push_str(", ");
+        written.push_str(given);
+    } else if name.suffix.is_some() {
+        written.push_str(", ");
+    }
+    written
+}
+
+// `family=Doe, given=Jane, prefix=van, useprefix=true`
+fn extended_name(name: &Name) -> String {
+    let mut parts = vec![("family", &name.family)];
+    parts.extend(name.given.iter().map(|g| ("given", g)));
+    parts.extend(name.prefix.iter().map(|p| ("prefix", p)));
+    parts.extend(name.suffix.iter().map(|s| ("suffix", s)));
+    parts.extend(name.options.iter().map(|(key, value)| (key.as_str(), value)));
+    let parts: Vec<String> = parts.into_iter().map(|(key, value)| {
+        let protected = value.contains(',') || value.contains('=')
+            || value.split_whitespace().any(|w| w.eq_ignore_ascii_case("and"));
+        match protected {
+            true => format!("{}={{{}}}", key, value),
+            false => format!("{}={}", key, value),
+        }
+    }).collect();
+    parts.join(", ")
+}
+
+// braces a family name which would otherwise be split up when reading it back
+fn protect(family: &str, alone: bool) -> String {
+    let words: Vec<&str> = family.split_whitespace().collect();
+    let lowercase = words.len() > 1 && words[..words.len() - 1].iter()
+        .any(|w| w.chars().next().is_some_and(char::is_lowercase));
+    let split = family.contains(',')
+        || words.iter().any(|w| w.eq_ignore_ascii_case("and"))
+        || lowercase
+        || (alone && words.len() > 1);
+    match split {
+        true => format!("{{{}}}", family),
+        false => family.to_string(),
+    }
+}
+
+/// Renders a list of ranges, e.g. `1--10, 15`
+pub fn ranges(ranges: &[Range]) -> String {
+    let ranges: Vec<String> = ranges.iter().map(|r| match r.end {
+        Some(ref end) => format!("{}--{}", r.start, end),
+        None => r.start.clone(),
+    }).collect();
+    ranges.join(", ")
+}
+
+/// Renders the value of a field as it is written between its delimiters
+pub fn value(field: &Field) -> String {
+    match *field {
+        Field::Author(ref names) | Field::Editor(ref names) => name_list(names),
+        Field::Date(ref date) | Field::UrlDate(ref date) | Field::EventDate(ref date) |
+        Field::OrigDate(ref date) => date.to_string(),
+        Field::Pages(ref pages) => ranges(pages),
+        Field::Title(ref text) | Field::MainTitle(ref text) | Field::JournalTitle(ref text) |
+        Field::BookTitle(ref text) => text.to_latex(),
+        Field::Url(ref s) | Field::Doi(ref s) |
+        Field::Eprint(ref s) | Field::Volume(ref s) | Field::Series(ref s) |
+        Field::Number(ref s) | Field::Issue(ref s) | Field::Edition(ref s) |
+        Field::Chapter(ref s) | Field::Publisher(ref s) | Field::Location(ref s) |
+        Field::Institution(ref s) | Field::Organization(ref s) | Field::Type(ref s) |
+        Field::EntrySet(ref s) | Field::Unknown(_, ref s) => s.clone(),
+    }
+}
+
+// fields under their BibLaTeX names, or the names they were given under
+fn biblatex_fields(entry: &Entry, canonical: bool) -> Vec<(String, String)> {
+    entry.fields().iter().map(|field| {
+        let name = match entry.original_names().get(field.name()) {
+            Some(original) if !canonical => original,
+            _ => field.name(),
+        };
+        (name.to_string(), value(field))
+    }).collect()
+}
+
+// BibTeX type closest to the type of the entry
+fn bibtex_type(entry: &Entry) -> &'static str {
+    match *entry {
+        Entry::Thesis(ref thesis) if thesis.kind == "mathesis" => "mastersthesis",
+        Entry::Thesis(_) => "phdthesis",
+        Entry::Report(_) => "techreport",
+        Entry::Book(_) | Entry::MvBook(_) | Entry::Collection(_) |
+        Entry::MvCollection(_) | Entry::Reference(_) | Entry::MvReference(_) => "book",
+        Entry::InBook(_) | Entry::BookInBook(_) | Entry::SuppBook(_) |
+        Entry::InCollection(_) | Entry::SuppCollection(_) | Entry::InReference(_) => "incollection",
+        Entry::Proceedings(_) | Entry::MvProceedings(_) => "proceedings",
+        Entry::Article(_) | Entry::Booklet(_) | Entry::InProceedings(_) |
+        Entry::Manual(_) | Entry::Unpublished(_) => entry.entry_type(),
+        _ => "misc",
+    }
+}
+
+// fields under their BibTeX names, in the case they were given in unless
+// `canonical` is set
+fn bibtex_fields(entry: &Entry, canonical: bool) -> Vec<(String, String)> {
+    let thesis = matches!(*entry, Entry::Thesis(_));
+    let mut fields = Vec::new();
+    for field in entry.fields() {
+        let name = match field {
+            // the kind of thesis or report is given by the entry type
+            Field::Type(ref kind) if ["phdthesis", "mathesis", "techreport"].contains(&kind.as_str()) => continue,
+            Field::JournalTitle(_) => "journal",
+            Field::Location(_) => "address",
+            Field::Institution(_) if thesis => "school",
+            Field::Unknown(ref name, _) if name == "annotation" => "annote",
+            Field::Unknown(ref name, _) if name == "eprinttype" => "archiveprefix",
+            Field::Unknown(ref name, _) if name == "eprintclass" => "primaryclass",
+            Field::Unknown(ref name, _) if name == "sortkey" => "key",
+            // BibTeX only knows the year and month
+            Field::Date(ref date) => {
+                fields.push(("year".to_string(), date.start.year.to_string()));
+                if let Some(month) = date.start.month {
+                    // months out of range are written as they are
+                    let month = match (month as usize).checked_sub(1).and_then(|i| MONTHS.get(i)) {
+                        Some(macro_name) => macro_name.to_string(),
+                        None => month.to_string(),
+                    };
+                    fields.push(("month".to_string(), month));
+                }
+                continue;
+            }
+            _ => field.name(),
+        };
+        fields.push((name.to_string(), value(&field)));
+    }
+    if !canonical {
+        for (name, _) in &mut fields {
+            let original = entry.original_names().iter().find(|n| n.1.eq_ignore_ascii_case(name));
+            if let Some((_, original)) = original {
+                *name = original.to_string();
+            }
+        }
+    }
+    fields
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parse_bib;
+    use biblatex::{Date, DatePart};
+
+    const BIB: &str = r#"@preamble{{\providecommand{\noopsort}[1]{}}}
+
+@article{baez/article,
+  author       = {Baez, John C. and Lauda, Aaron D.},
+  title        = {Higher-Dimensional Algebra {V}: 2-Groups},
+  journaltitle = {Theory and Applications of Categories},
+  volume       = {12},
+  pages        = {423--491},
+  date         = {2004},
+  keywords     = {category {theory}},
+}
+
+@thesis{doe,
+  author      = {Doe, Jane},
+  title       = {On Things},
+  type        = {phdthesis},
+  institution = {University of Nowhere},
+  location    = {Nowhere},
+  date        = {2010},
+}
+"#;
+
+    #[test]
+    fn round_trip() {
+        let bib = parse_bib(BIB.as_bytes()).unwrap();
+        let written = Writer::default().bibliography(&bib);
+        assert_eq!(written, BIB);
+        assert_eq!(parse_bib(written.as_bytes()).unwrap(), bib);
+    }
+
+    #[test]
+    fn bibtex() {
+        let bib = parse_bib(BIB.as_bytes()).unwrap();
+        let writer = Writer {
+            dialect: Dialect::BibTeX,
+            indent: "\t".to_string(),
+            align_equals: false,
+            delimiter: Delimiter::Quotes,
+            trailing_comma: false,
+            .. Writer::default()
+        };
+        let (ref key, ref entry) = bib.entries()[1];
+        let expected = "@phdthesis{doe,\n\tauthor = \"Doe, Jane\",\n\ttitle = \"On Things\",\n\tschool = \"University of Nowhere\",\n\taddress = \"Nowhere\",\n\tyear = \"2010\"\n}";
+        assert_eq!(writer.entry(key, entry), expected);
+
+        let bib = parse_bib(b"@misc{m, title = {T}, editor = {Doe, Jane}, date = {2010-03-14}}").unwrap();
+        let (ref key, ref entry) = bib.entries()[0];
+        let expected = "@misc{m,\n\teditor = \"Doe, Jane\",\n\ttitle = \"T\",\n\tyear = \"2010\",\n\tmonth = mar\n}";
+        assert_eq!(writer.entry(key, entry), expected);
+
+        // dates built by hand may have months which BibTeX has no macro for
+        let mut entry = entry.clone();
+        let start = DatePart { month: Some(13), .. DatePart::year(800) };
+        entry.set(Field::Date(Date { start, .. Date::default() })).unwrap();
+        let expected = "@misc{m,\n\teditor = \"Doe, Jane\",\n\ttitle = \"T\",\n\tyear = \"800\",\n\tmonth = \"13\"\n}";
+        assert_eq!(writer.entry(key, &entry), expected);
+    }
+
+    #[test]
+    fn aliases() {
+        let raw = b"@article{a, author = {Doe, Jane}, title = {T}, journal = {J}, year = 2010, annote = {N}}";
+        let bib = parse_bib(raw).unwrap();
+        let (ref key, ref entry) = bib.entries()[0];
+        let writer = Writer { align_equals: false, .. Writer::default() };
+        let expected = "@article{a,\n  author = {Doe, Jane},\n  title = {T},\n  journal = {J},\n  date = {2010},\n  annote = {N},\n}";
+        assert_eq!(writer.entry(key, entry), expected);
+
+        let writer = Writer { canonical_names: true, .. writer };
+        let expected = "@article{a,\n  author = {Doe, Jane},\n  title = {T},\n  journaltitle = {J},\n  date = {2010},\n  annotation = {N},\n}";
+        assert_eq!(writer.entry(key, entry), expected);
+
+        let writer = Writer { dialect: Dialect::BibTeX, .. writer };
+        let expected = "@article{a,\n  author = {Doe, Jane},\n  title = {T},\n  journal = {J},\n  year = {2010},\n  annote = {N},\n}";
+        assert_eq!(writer.entry(key, entry), expected);
+    }
+
+    #[test]
+    fn field_order() {
+        let bib = parse_bib(b"@misc{m, note = {N}, title = {T}, author = {Doe, Jane}, year = 2010}").unwrap();
+        let writer = Writer {
+            field_order: vec!["date".to_string(), "note".to_string()],
+            align_equals: false,
+            .. Writer::default()
+        };
+        let (ref key, ref entry) = bib.entries()[0];
+        let expected = "@misc{m,\n  date = {2010},\n  note = {N},\n  title = {T},\n  author = {Doe, Jane},\n}";
+        assert_eq!(writer.entry(key, entry), expected);
+    }
+
+    #[test]
+    fn names() {
+        let person = |given: Option<&str>, prefix: Option<&str>, family: &str, suffix: Option<&str>| Name {
+            family: family.to_string(),
+            given: given.map(|s| s.to_string()),
+            prefix: prefix.map(|s| s.to_string()),
+            suffix: suffix.map(|s| s.to_string()),
+            .. Name::default()
+        };
+        let mut extended = person(Some("Jane"), Some("van"), "Doe, Jr", None);
+        extended.options.push(("useprefix".to_string(), "true".to_string()));
+        let names = NameList {
+            names: vec![
+                person(Some("Saunders"), None, "Mac Lane", None),
+                person(None, None, "Barnes and Noble", None),
+                person(None, None, "Acme Corporation", None),
+                person(None, None, "Knuth", None),
+                person(Some("Ludwig"), Some("van"), "Beethoven", None),
+                person(Some("John"), None, "Smith", Some("Jr.")),
+                person(Some("Jean"), None, "de la Fontaine", None),
+                extended,
+            ],
+            truncated: true,
+        };
+        assert_eq!(name_list(&names), "Mac Lane, Saunders and {Barnes and Noble} and {Acme Corporation} \
+                                       and Knuth and van Beethoven, Ludwig and Smith, Jr., John \
+                                       and {de la Fontaine}, Jean \
+                                       and family={Doe, Jr}, given=Jane, prefix=van, useprefix=true and others");
+
+        let written = format!("@misc{{m, author = {{{}}}, title = {{T}}, year = 2010}}", name_list(&names));
+        let bib = parse_bib(written.as_bytes()).unwrap();
+        assert_eq!(bib.entries()[0].1.author(), Some(&names));
+    }
+
+    #[test]
+    fn encode_latex() {
+        let raw = "@misc{m, author = {Gödel, Kurt}, title = {Über Sätze – α}, url = {https://example.org/gödel}, year = 1931}";
+        let bib = parse_bib(raw.as_bytes()).unwrap();
+        let writer = Writer {
+            dialect: Dialect::BibTeX,
+            encode_latex: Some(Fallback::Replace("?".to_string())),
+            .. Writer::default()
+        };
+        let (ref key, ref entry) = bib.entries()[0];
+        let expected = "@misc{m,\n  author = {G{\\\"o}del, Kurt},\n  title  = {{\\\"U}ber S{\\\"a}tze -- ?},\n  \
+                        year   = {1931},\n  url    = {https://example.org/gödel},\n}";
+        assert_eq!(writer.entry(key, entry), expected);
+    }
+
+    #[test]
+    fn page_ranges() {
+        let pages = vec![
+            Range { start: "1".to_string(), end: Some("10".to_string()) },
+            Range { start: "15".to_string(), end: None },
+        ];
+        assert_eq!(ranges(&pages), "1--10, 15");
+    }
+}